@@ -1,12 +1,12 @@
 /*!
-The pluggable io stream. now support: stdio, string io, in memory pipe, in memory line pipe.
+The pluggable io stream. now support: stdio, string io, file io, child process io, unix domain socket, tcp, os pipe, named fifo, mmap file, tee, chain, null, in memory pipe, in memory ring pipe, in memory line pipe, in memory broadcast pipe.
 
 # Features
 
-- support common operation: stdin, stdout, stderr, stringin, stringout, pipein, pipeout, linepipein and linepipeout.
+- support common operation: stdin, stdout, stderr, stringin, stringout, filein, fileout, childin, childout, pipein, pipeout, linepipein and linepipeout.
 - thin interface
 - support testing io stream
-- minimum support rustc 1.60.0 (7737e0b5c 2022-04-04)
+- minimum support rustc 1.63.0 (4b91a6ea7 2022-08-08)
 
 # Examples
 
@@ -124,6 +124,7 @@ pub mod medium;
 use std::borrow::Borrow;
 use std::fmt::Debug;
 use std::io::{BufRead, Result, Write};
+use std::ops::ControlFlow;
 use std::panic::{RefUnwindSafe, UnwindSafe};
 
 //----------------------------------------------------------------------
@@ -146,6 +147,75 @@ pub trait StreamIn: Send + Sync + UnwindSafe + RefUnwindSafe + Debug {
     /// byte (the `0xA` byte) or `CRLF` (`0xD`, `0xA` bytes) at the end.
     /// This behaves the same as `std::io::BufRead::lines()`.
     fn lines(&self) -> Box<dyn NextLine + '_>;
+
+    /// Calls `f` with each line of the stream, until EOF or `f` returns
+    /// `ControlFlow::Break`. The line does *not* have a newline at the end,
+    /// the same as `lines()`.
+    /// The default reads by `lock_bufread()`, reusing one `String`.
+    fn for_each_line(&self, f: &mut dyn FnMut(&str) -> Result<ControlFlow<()>>) -> Result<()> {
+        for_each_line_from(&mut *self.lock_bufread(), f)
+    }
+
+    /// Reads the next line into `buf`, which is cleared first.
+    /// The line does *not* have a newline at the end, the same as `lines()`.
+    /// Returns `false` on EOF.
+    fn read_line_into(&self, buf: &mut String) -> Result<bool> {
+        read_line_from(&mut *self.lock_bufread(), buf)
+    }
+}
+
+/// write each of `lines` and a newline to the locked stream `a`.
+pub(crate) fn write_lines_to<W, I>(a: &mut W, lines: I) -> Result<()>
+where
+    W: Write + ?Sized,
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    for string in lines {
+        a.write_all(string.as_ref().as_bytes())?;
+        a.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// call `f` with each line of the locked stream `a`, reusing one `String`.
+pub(crate) fn for_each_line_from<R>(
+    a: &mut R,
+    f: &mut dyn FnMut(&str) -> Result<ControlFlow<()>>,
+) -> Result<()>
+where
+    R: BufRead + ?Sized,
+{
+    let mut buf = String::new();
+    while read_line_from(a, &mut buf)? {
+        if f(&buf)?.is_break() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// read the next line of the locked stream `a` into `buf`, without the newline.
+pub(crate) fn read_line_from<R>(a: &mut R, buf: &mut String) -> Result<bool>
+where
+    R: BufRead + ?Sized,
+{
+    buf.clear();
+    if a.read_line(buf)? == 0 {
+        return Ok(false);
+    }
+    trim_newline(buf);
+    Ok(true)
+}
+
+/// remove the trailing `LF` or `CRLF`, like `std::io::BufRead::lines()`.
+fn trim_newline(buf: &mut String) {
+    if buf.ends_with('\n') {
+        buf.pop();
+        if buf.ends_with('\r') {
+            buf.pop();
+        }
+    }
 }
 
 /// A trait for writable streams.
@@ -162,6 +232,25 @@ pub trait StreamOut: Send + Sync + UnwindSafe + RefUnwindSafe + Debug {
     /// `CRLF` (`0xD`, `0xA` bytes) at the end.
     fn write_line(&self, string: String) -> Result<()>;
 
+    /// Writes the lines to the stream, in a batch.
+    /// Each string should *not* have a newline byte at the end.
+    /// The default calls `write_line()` for each line; the media override
+    /// this to write them under a single lock.
+    /// This takes a `Vec`, not an `impl IntoIterator`, to keep the trait
+    /// object safe.
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        for string in lines {
+            self.write_line(string)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a borrowed line to the stream.
+    /// The byte media copy it without allocating a `String`.
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        self.write_line(string.to_string())
+    }
+
     /// Flushes the stream.
     fn flush_line(&self) -> Result<()>;
 }
@@ -191,6 +280,25 @@ pub trait StreamErr: Send + Sync + UnwindSafe + RefUnwindSafe + Debug {
     /// `CRLF` (`0xD`, `0xA` bytes) at the end.
     fn write_line(&self, string: String) -> Result<()>;
 
+    /// Writes the lines to the stream, in a batch.
+    /// Each string should *not* have a newline byte at the end.
+    /// The default calls `write_line()` for each line; the media override
+    /// this to write them under a single lock.
+    /// This takes a `Vec`, not an `impl IntoIterator`, to keep the trait
+    /// object safe.
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        for string in lines {
+            self.write_line(string)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a borrowed line to the stream.
+    /// The byte media copy it without allocating a `String`.
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        self.write_line(string.to_string())
+    }
+
     /// Flushes the stream.
     fn flush_line(&self) -> Result<()>;
 }
@@ -206,6 +314,19 @@ pub trait StreamErrLock: Write {
     }
 }
 
+//----------------------------------------------------------------------
+/// The buffering mode of an output stream, like libc stdio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BufferMode {
+    /// The data is sent on each write.
+    Unbuffered,
+    /// The data is sent on each newline, or when the buffer is full.
+    Line,
+    /// The data is sent when the buffer is full, or on flush.
+    #[default]
+    Full,
+}
+
 //----------------------------------------------------------------------
 /// A struct that holds the three streams.
 #[derive(Debug)]
@@ -283,6 +404,18 @@ impl RunnelIoe {
 ///     .build();
 /// ```
 ///
+/// ## Example: quiet stderr
+///
+/// build RunnelIoe has [std::io::stdin()], [std::io::stdout()],
+/// [medium::nullio::NullErr],
+///
+/// ```rust
+/// use runnel::RunnelIoeBuilder;
+/// let sioe = RunnelIoeBuilder::new()
+///     .pg_err_null()
+///     .build();
+/// ```
+///
 /// ## Example: stdio and pipe
 ///
 /// This case is multi-threads.
@@ -322,6 +455,22 @@ impl RunnelIoe {
 ///     Ok(())
 /// }
 /// ```
+/// ## Example: fill fileio
+///
+/// build RunnelIoe has [medium::fileio::FileIn],
+/// [medium::fileio::FileOut], [std::io::stderr()],
+///
+/// ```rust,no_run
+/// use runnel::RunnelIoeBuilder;
+///
+/// fn run() -> std::io::Result<()> {
+///     let sioe = RunnelIoeBuilder::new()
+///         .pg_in_file("input.txt")?
+///         .pg_out_file("output.txt")?
+///         .build();
+///     Ok(())
+/// }
+/// ```
 ///
 #[derive(Debug)]
 pub struct RunnelIoeBuilder {
@@ -387,6 +536,43 @@ impl RunnelIoeBuilder {
             .pg_out(StringOut::default())
             .pg_err(StringErr::default())
     }
+    /// fill with nullio, the input is empty and the outputs are discarded
+    pub fn fill_nullio(self) -> Self {
+        use crate::medium::nullio::*;
+        self.pg_in(EmptyIn).pg_out(NullOut).pg_err(NullErr)
+    }
+    /// set pluggable input stream that is empty
+    pub fn pg_in_empty(self) -> Self {
+        self.pg_in(medium::nullio::EmptyIn)
+    }
+    /// set pluggable output stream that discards everything
+    pub fn pg_out_null(self) -> Self {
+        self.pg_out(medium::nullio::NullOut)
+    }
+    /// set pluggable error stream that discards everything
+    pub fn pg_err_null(self) -> Self {
+        self.pg_err(medium::nullio::NullErr)
+    }
+    /// set pluggable input stream with the file at `path`
+    pub fn pg_in_file<P: AsRef<std::path::Path>>(self, path: P) -> Result<Self> {
+        Ok(self.pg_in(medium::fileio::FileIn::open(path)?))
+    }
+    /// set pluggable output stream with the file at `path`, truncating it
+    pub fn pg_out_file<P: AsRef<std::path::Path>>(self, path: P) -> Result<Self> {
+        Ok(self.pg_out(medium::fileio::FileOut::create(path)?))
+    }
+    /// set pluggable output stream with the file at `path`, appending to it
+    pub fn pg_out_file_append<P: AsRef<std::path::Path>>(self, path: P) -> Result<Self> {
+        Ok(self.pg_out(medium::fileio::FileOut::append(path)?))
+    }
+    /// set pluggable error stream with the file at `path`, truncating it
+    pub fn pg_err_file<P: AsRef<std::path::Path>>(self, path: P) -> Result<Self> {
+        Ok(self.pg_err(medium::fileio::FileErr::create(path)?))
+    }
+    /// set pluggable error stream with the file at `path`, appending to it
+    pub fn pg_err_file_append<P: AsRef<std::path::Path>>(self, path: P) -> Result<Self> {
+        Ok(self.pg_err(medium::fileio::FileErr::append(path)?))
+    }
 }
 
 impl Default for RunnelIoeBuilder {
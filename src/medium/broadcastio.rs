@@ -0,0 +1,1045 @@
+//!
+//! The in-memory broadcast stream. One writer, many independent readers.
+//!
+//! Each subscriber sees every chunk written after it subscribed.
+//! The chunks are shared by reference-counting, they are not copied per reader.
+//!
+//! When the readers are slower than the writer, [`BroadcastPolicy`] decides
+//! what to do.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use runnel::medium::broadcastio::{line_broadcast_pipe, BroadcastPolicy};
+//! use runnel::*;
+//!
+//! let (sout, sin_a) = line_broadcast_pipe(4, BroadcastPolicy::default());
+//! let sin_b = sout.subscribe();
+//! let handler = std::thread::spawn(move || {
+//!     sout.write_line("ABCDE".to_string()).unwrap();
+//!     sout.write_line("efgh".to_string()).unwrap();
+//!     sout.flush_line().unwrap();
+//! });
+//! for sin in [sin_a, sin_b] {
+//!     let mut lines_iter = sin.lines().map(|l| l.unwrap());
+//!     assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+//!     assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+//!     assert_eq!(lines_iter.next(), None);
+//! }
+//! assert!(handler.join().is_ok());
+//! ```
+//!
+use super::linepipeio::MSG_CHUNK_SZ;
+use super::pipeio::BUF_SZ;
+use crate::*;
+
+use std::collections::VecDeque;
+use std::io::{BufRead, Read, Write};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+//----------------------------------------------------------------------
+/// The policy for the readers that are slower than the writer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BroadcastPolicy {
+    /// the writer waits for the slowest reader.
+    #[default]
+    SlowestGoverns,
+    /// the writer does not wait, and a lagging reader loses the oldest chunks.
+    /// The number of them is got by `dropped_chunks()`.
+    LagAndDrop,
+}
+
+/// create in-memory broadcast stream and return ([`BroadcastPipeOut`], [`BroadcastPipeIn`]).
+///
+/// The `sz` is the number of the chunks kept for the readers.
+/// More readers are got by [`BroadcastPipeOut::subscribe()`].
+///
+/// [`BroadcastPipeOut`]: BroadcastPipeOut
+/// [`BroadcastPipeIn`]: BroadcastPipeIn
+/// [`BroadcastPipeOut::subscribe()`]: BroadcastPipeOut::subscribe
+///
+pub fn broadcast_pipe(sz: usize, policy: BroadcastPolicy) -> (BroadcastPipeOut, BroadcastPipeIn) {
+    let hub = Arc::new(Hub::new(sz, policy));
+    let sin = BroadcastPipeIn::subscribe(&hub);
+    (
+        BroadcastPipeOut(LockableBroadcastPipeOut::with(RawBroadcastPipeOut::new(
+            hub,
+        ))),
+        sin,
+    )
+}
+
+/// create in-memory broadcast text line stream and return ([`LineBroadcastPipeOut`], [`LineBroadcastPipeIn`]).
+///
+/// The `sz` is the number of the chunks kept for the readers.
+/// More readers are got by [`LineBroadcastPipeOut::subscribe()`].
+///
+/// [`LineBroadcastPipeOut`]: LineBroadcastPipeOut
+/// [`LineBroadcastPipeIn`]: LineBroadcastPipeIn
+/// [`LineBroadcastPipeOut::subscribe()`]: LineBroadcastPipeOut::subscribe
+///
+pub fn line_broadcast_pipe(
+    sz: usize,
+    policy: BroadcastPolicy,
+) -> (LineBroadcastPipeOut, LineBroadcastPipeIn) {
+    let hub = Arc::new(Hub::new(sz, policy));
+    let sin = LineBroadcastPipeIn::subscribe(&hub);
+    (
+        LineBroadcastPipeOut(LockableLineBroadcastPipeOut::with(
+            RawLineBroadcastPipeOut::new(hub),
+        )),
+        sin,
+    )
+}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamIn
+/// The in-memory broadcast input stream.
+#[derive(Debug)]
+pub struct BroadcastPipeIn(LockableBroadcastPipeIn);
+impl BroadcastPipeIn {
+    fn subscribe(hub: &Arc<Hub<Vec<u8>>>) -> Self {
+        Self(LockableBroadcastPipeIn::with(Subscription::new(hub)))
+    }
+    /// Returns the number of the chunks lost by [`BroadcastPolicy::LagAndDrop`].
+    pub fn dropped_chunks(&self) -> u64 {
+        self.0.sub.dropped_chunks()
+    }
+}
+impl StreamIn for BroadcastPipeIn {
+    fn lock_bufread(&self) -> Box<dyn BufRead + '_> {
+        Box::new(BroadcastPipeInLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn lines(&self) -> Box<dyn NextLine + '_> {
+        let a = self.0.inner.lock().unwrap().take().unwrap();
+        let b = a.lines();
+        Box::new(Lines { buf: b })
+    }
+}
+
+/// A locked reference to `BroadcastPipeIn`
+#[derive(Debug)]
+pub struct BroadcastPipeInLock<'a>(LockableBroadcastPipeInLock<'a>);
+impl Read for BroadcastPipeInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+impl BufRead for BroadcastPipeInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.0.fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.0.consume(amt)
+    }
+}
+
+/// The in-memory broadcast text line input stream.
+#[derive(Debug)]
+pub struct LineBroadcastPipeIn(LockableLineBroadcastPipeIn);
+impl LineBroadcastPipeIn {
+    fn subscribe(hub: &Arc<Hub<Vec<String>>>) -> Self {
+        Self(LockableLineBroadcastPipeIn::with(Subscription::new(hub)))
+    }
+    /// Returns the number of the chunks lost by [`BroadcastPolicy::LagAndDrop`].
+    pub fn dropped_chunks(&self) -> u64 {
+        self.0.sub.dropped_chunks()
+    }
+}
+impl StreamIn for LineBroadcastPipeIn {
+    fn lock_bufread(&self) -> Box<dyn BufRead + '_> {
+        Box::new(LineBroadcastPipeInLock(self.0.lock()))
+    }
+    #[inline(always)]
+    fn is_line_pipe(&self) -> bool {
+        true
+    }
+    fn lines(&self) -> Box<dyn NextLine + '_> {
+        let a = self.0.inner.lock().unwrap().take().unwrap();
+        Box::new(LineLines { buf: a })
+    }
+}
+
+/// A locked reference to `LineBroadcastPipeIn`
+#[derive(Debug)]
+pub struct LineBroadcastPipeInLock<'a>(LockableLineBroadcastPipeInLock<'a>);
+impl Read for LineBroadcastPipeInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+impl BufRead for LineBroadcastPipeInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.0.fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.0.consume(amt)
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamOut
+/// The in-memory broadcast output stream.
+#[derive(Debug)]
+pub struct BroadcastPipeOut(LockableBroadcastPipeOut);
+impl BroadcastPipeOut {
+    /// Returns a new reader. It sees the chunks sent after this.
+    pub fn subscribe(&self) -> BroadcastPipeIn {
+        BroadcastPipeIn::subscribe(&self.0.hub)
+    }
+}
+impl StreamOut for BroadcastPipeOut {
+    #[inline(always)]
+    fn lock(&self) -> Box<dyn StreamOutLock + '_> {
+        Box::new(BroadcastPipeOutLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn write_line(&self, string: String) -> Result<()> {
+        self.0.lock().inner.write_line(&string)
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        self.0.lock().inner.write_lines(lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        self.0.lock().inner.write_line(string)
+    }
+    fn flush_line(&self) -> Result<()> {
+        self.lock().flush()
+    }
+}
+
+/// A locked reference to `BroadcastPipeOut`
+#[derive(Debug)]
+pub struct BroadcastPipeOutLock<'a>(LockableBroadcastPipeOutLock<'a>);
+impl StreamOutLock for BroadcastPipeOutLock<'_> {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        self.0.buffer()
+    }
+}
+impl Write for BroadcastPipeOutLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// The in-memory broadcast text line output stream.
+#[derive(Debug)]
+pub struct LineBroadcastPipeOut(LockableLineBroadcastPipeOut);
+impl LineBroadcastPipeOut {
+    /// Returns a new reader. It sees the chunks sent after this.
+    pub fn subscribe(&self) -> LineBroadcastPipeIn {
+        LineBroadcastPipeIn::subscribe(&self.0.hub)
+    }
+}
+impl StreamOut for LineBroadcastPipeOut {
+    #[inline(always)]
+    fn lock(&self) -> Box<dyn StreamOutLock + '_> {
+        Box::new(LineBroadcastPipeOutLock(self.0.lock()))
+    }
+    #[inline(always)]
+    fn is_line_pipe(&self) -> bool {
+        true
+    }
+    fn write_line(&self, string: String) -> Result<()> {
+        self.0.lock().inner.write_line(string)
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        self.0.lock().inner.write_lines(lines)
+    }
+    fn flush_line(&self) -> Result<()> {
+        self.0.lock().inner.flush_line()
+    }
+}
+
+/// A locked reference to `LineBroadcastPipeOut`
+#[derive(Debug)]
+pub struct LineBroadcastPipeOutLock<'a>(LockableLineBroadcastPipeOutLock<'a>);
+impl StreamOutLock for LineBroadcastPipeOutLock<'_> {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        self.0.buffer()
+    }
+}
+impl Write for LineBroadcastPipeOutLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamErr
+/// The in-memory broadcast error stream.
+#[derive(Debug)]
+pub struct BroadcastPipeErr(LockableBroadcastPipeOut);
+impl BroadcastPipeErr {
+    /// Returns a new reader. It sees the chunks sent after this.
+    pub fn subscribe(&self) -> BroadcastPipeIn {
+        BroadcastPipeIn::subscribe(&self.0.hub)
+    }
+}
+impl StreamErr for BroadcastPipeErr {
+    #[inline(always)]
+    fn lock(&self) -> Box<dyn StreamErrLock + '_> {
+        Box::new(BroadcastPipeErrLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn write_line(&self, string: String) -> Result<()> {
+        self.0.lock().inner.write_line(&string)
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        self.0.lock().inner.write_lines(lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        self.0.lock().inner.write_line(string)
+    }
+    fn flush_line(&self) -> Result<()> {
+        self.lock().flush()
+    }
+}
+
+impl std::convert::From<BroadcastPipeOut> for BroadcastPipeErr {
+    #[inline(always)]
+    fn from(a: BroadcastPipeOut) -> Self {
+        Self(a.0)
+    }
+}
+
+/// A locked reference to `BroadcastPipeErr`
+#[derive(Debug)]
+pub struct BroadcastPipeErrLock<'a>(LockableBroadcastPipeOutLock<'a>);
+impl StreamErrLock for BroadcastPipeErrLock<'_> {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        self.0.buffer()
+    }
+}
+impl Write for BroadcastPipeErrLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// The in-memory broadcast text line error stream.
+#[derive(Debug)]
+pub struct LineBroadcastPipeErr(LockableLineBroadcastPipeOut);
+impl LineBroadcastPipeErr {
+    /// Returns a new reader. It sees the chunks sent after this.
+    pub fn subscribe(&self) -> LineBroadcastPipeIn {
+        LineBroadcastPipeIn::subscribe(&self.0.hub)
+    }
+}
+impl StreamErr for LineBroadcastPipeErr {
+    #[inline(always)]
+    fn lock(&self) -> Box<dyn StreamErrLock + '_> {
+        Box::new(LineBroadcastPipeErrLock(self.0.lock()))
+    }
+    #[inline(always)]
+    fn is_line_pipe(&self) -> bool {
+        true
+    }
+    fn write_line(&self, string: String) -> Result<()> {
+        self.0.lock().inner.write_line(string)
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        self.0.lock().inner.write_lines(lines)
+    }
+    fn flush_line(&self) -> Result<()> {
+        self.0.lock().inner.flush_line()
+    }
+}
+
+impl std::convert::From<LineBroadcastPipeOut> for LineBroadcastPipeErr {
+    #[inline(always)]
+    fn from(a: LineBroadcastPipeOut) -> Self {
+        Self(a.0)
+    }
+}
+
+/// A locked reference to `LineBroadcastPipeErr`
+#[derive(Debug)]
+pub struct LineBroadcastPipeErrLock<'a>(LockableLineBroadcastPipeOutLock<'a>);
+impl StreamErrLock for LineBroadcastPipeErrLock<'_> {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        self.0.buffer()
+    }
+}
+impl Write for LineBroadcastPipeErrLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+#[derive(Debug)]
+struct LockableBroadcastPipeIn {
+    sub: Arc<Subscription<Vec<u8>>>,
+    inner: Mutex<Option<RawBroadcastPipeIn>>,
+}
+impl LockableBroadcastPipeIn {
+    fn with(sub: Subscription<Vec<u8>>) -> Self {
+        let sub = Arc::new(sub);
+        LockableBroadcastPipeIn {
+            inner: Mutex::new(Some(RawBroadcastPipeIn::new(sub.clone()))),
+            sub,
+        }
+    }
+    pub fn lock(&self) -> LockableBroadcastPipeInLock<'_> {
+        LockableBroadcastPipeInLock {
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LockableBroadcastPipeInLock<'a> {
+    inner: MutexGuard<'a, Option<RawBroadcastPipeIn>>,
+}
+impl Read for LockableBroadcastPipeInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.as_mut().unwrap().read(buf)
+    }
+}
+impl BufRead for LockableBroadcastPipeInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.as_mut().unwrap().fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.inner.as_mut().unwrap().consume(amt)
+    }
+}
+
+#[derive(Debug)]
+struct LockableLineBroadcastPipeIn {
+    sub: Arc<Subscription<Vec<String>>>,
+    inner: Mutex<Option<RawLineBroadcastPipeIn>>,
+}
+impl LockableLineBroadcastPipeIn {
+    fn with(sub: Subscription<Vec<String>>) -> Self {
+        let sub = Arc::new(sub);
+        LockableLineBroadcastPipeIn {
+            inner: Mutex::new(Some(RawLineBroadcastPipeIn::new(sub.clone()))),
+            sub,
+        }
+    }
+    pub fn lock(&self) -> LockableLineBroadcastPipeInLock<'_> {
+        LockableLineBroadcastPipeInLock {
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LockableLineBroadcastPipeInLock<'a> {
+    inner: MutexGuard<'a, Option<RawLineBroadcastPipeIn>>,
+}
+impl Read for LockableLineBroadcastPipeInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.as_mut().unwrap().read(buf)
+    }
+}
+impl BufRead for LockableLineBroadcastPipeInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.as_mut().unwrap().fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.inner.as_mut().unwrap().consume(amt)
+    }
+}
+
+#[derive(Debug)]
+struct LockableBroadcastPipeOut {
+    hub: Arc<Hub<Vec<u8>>>,
+    inner: Mutex<RawBroadcastPipeOut>,
+}
+impl LockableBroadcastPipeOut {
+    fn with(a: RawBroadcastPipeOut) -> Self {
+        LockableBroadcastPipeOut {
+            hub: a.hub.clone(),
+            inner: Mutex::new(a),
+        }
+    }
+    pub fn lock(&self) -> LockableBroadcastPipeOutLock<'_> {
+        LockableBroadcastPipeOutLock {
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LockableBroadcastPipeOutLock<'a> {
+    inner: MutexGuard<'a, RawBroadcastPipeOut>,
+}
+impl LockableBroadcastPipeOutLock<'_> {
+    #[inline(always)]
+    pub fn buffer(&self) -> &[u8] {
+        self.inner.buf.as_slice()
+    }
+}
+impl Write for LockableBroadcastPipeOutLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.inner.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[derive(Debug)]
+struct LockableLineBroadcastPipeOut {
+    hub: Arc<Hub<Vec<String>>>,
+    inner: Mutex<RawLineBroadcastPipeOut>,
+}
+impl LockableLineBroadcastPipeOut {
+    fn with(a: RawLineBroadcastPipeOut) -> Self {
+        LockableLineBroadcastPipeOut {
+            hub: a.hub.clone(),
+            inner: Mutex::new(a),
+        }
+    }
+    pub fn lock(&self) -> LockableLineBroadcastPipeOutLock<'_> {
+        LockableLineBroadcastPipeOutLock {
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LockableLineBroadcastPipeOutLock<'a> {
+    inner: MutexGuard<'a, RawLineBroadcastPipeOut>,
+}
+impl LockableLineBroadcastPipeOutLock<'_> {
+    #[inline(always)]
+    pub fn buffer(&self) -> &[u8] {
+        self.inner.partial.as_slice()
+    }
+}
+impl Write for LockableLineBroadcastPipeOutLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.inner.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub struct Lines {
+    buf: std::io::Lines<RawBroadcastPipeIn>,
+}
+impl Iterator for Lines {
+    type Item = Result<String>;
+    fn next(&mut self) -> Option<Result<String>> {
+        self.buf.next()
+    }
+}
+impl NextLine for Lines {}
+
+pub struct LineLines {
+    buf: RawLineBroadcastPipeIn,
+}
+impl Iterator for LineLines {
+    type Item = Result<String>;
+    fn next(&mut self) -> Option<Result<String>> {
+        self.buf.next()
+    }
+}
+impl NextLine for LineLines {}
+
+//----------------------------------------------------------------------
+fn invalid_utf8() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        "stream did not contain valid UTF-8",
+    )
+}
+
+fn no_reader() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::BrokenPipe, "no reader is subscribed")
+}
+
+#[derive(Debug)]
+struct RawBroadcastPipeIn {
+    sub: Arc<Subscription<Vec<u8>>>,
+    chunk: Option<Arc<Vec<u8>>>,
+    pos: usize,
+}
+impl RawBroadcastPipeIn {
+    fn new(sub: Arc<Subscription<Vec<u8>>>) -> Self {
+        Self {
+            sub,
+            chunk: None,
+            pos: 0,
+        }
+    }
+}
+impl Read for RawBroadcastPipeIn {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = {
+            let src = self.fill_buf()?;
+            let len = src.len().min(buf.len());
+            buf[..len].copy_from_slice(&src[..len]);
+            len
+        };
+        self.consume(len);
+        Ok(len)
+    }
+}
+impl BufRead for RawBroadcastPipeIn {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        while self.pos >= self.chunk.as_ref().map(|a| a.len()).unwrap_or(0) {
+            self.pos = 0;
+            self.chunk = self.sub.recv();
+            if self.chunk.is_none() {
+                return Ok(&[]);
+            }
+        }
+        Ok(&self.chunk.as_ref().unwrap()[self.pos..])
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        let len = self.chunk.as_ref().map(|a| a.len()).unwrap_or(0);
+        self.pos = (self.pos + amt).min(len);
+    }
+}
+
+#[derive(Debug)]
+struct RawLineBroadcastPipeIn {
+    sub: Arc<Subscription<Vec<String>>>,
+    chunk: Option<Arc<Vec<String>>>,
+    idx: usize,
+    // the bytes of the current line with '\n', for BufRead
+    bytes: Vec<u8>,
+    pos: usize,
+}
+impl RawLineBroadcastPipeIn {
+    fn new(sub: Arc<Subscription<Vec<String>>>) -> Self {
+        Self {
+            sub,
+            chunk: None,
+            idx: 0,
+            bytes: Vec::new(),
+            pos: 0,
+        }
+    }
+    fn next(&mut self) -> Option<Result<String>> {
+        if self.pos < self.bytes.len() {
+            // the rest of the line that is partially read as bytes
+            let mut v = self.bytes.split_off(self.pos);
+            self.bytes.clear();
+            self.pos = 0;
+            v.pop();
+            return Some(String::from_utf8(v).map_err(|_| invalid_utf8()));
+        }
+        self.next_line().map(|a| Ok(a.to_string()))
+    }
+    fn next_line(&mut self) -> Option<&str> {
+        while self.idx >= self.chunk.as_ref().map(|a| a.len()).unwrap_or(0) {
+            self.idx = 0;
+            self.chunk = self.sub.recv();
+            self.chunk.as_ref()?;
+        }
+        self.idx += 1;
+        Some(self.chunk.as_ref().unwrap()[self.idx - 1].as_str())
+    }
+}
+impl Read for RawLineBroadcastPipeIn {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = {
+            let src = self.fill_buf()?;
+            let len = src.len().min(buf.len());
+            buf[..len].copy_from_slice(&src[..len]);
+            len
+        };
+        self.consume(len);
+        Ok(len)
+    }
+}
+impl BufRead for RawLineBroadcastPipeIn {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        if self.pos >= self.bytes.len() {
+            let mut bytes = std::mem::take(&mut self.bytes);
+            bytes.clear();
+            if let Some(line) = self.next_line() {
+                bytes.extend_from_slice(line.as_bytes());
+                bytes.push(b'\n');
+            }
+            self.bytes = bytes;
+            self.pos = 0;
+        }
+        Ok(&self.bytes[self.pos..])
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.bytes.len());
+    }
+}
+
+#[derive(Debug)]
+struct RawBroadcastPipeOut {
+    buf: Vec<u8>,
+    hub: Arc<Hub<Vec<u8>>>,
+}
+impl RawBroadcastPipeOut {
+    fn new(hub: Arc<Hub<Vec<u8>>>) -> Self {
+        Self {
+            buf: Vec::new(),
+            hub,
+        }
+    }
+    /// write `string` and a newline, they are sent in the same chunk.
+    fn write_line(&mut self, string: &str) -> Result<()> {
+        if self.buf.len() >= BUF_SZ {
+            self.flush()?;
+        }
+        self.buf.extend_from_slice(string.as_bytes());
+        self.buf.push(b'\n');
+        Ok(())
+    }
+    /// write the lines, each line is sent in the same chunk with its newline.
+    fn write_lines(&mut self, lines: Vec<String>) -> Result<()> {
+        for string in lines {
+            self.write_line(&string)?;
+        }
+        Ok(())
+    }
+}
+impl Drop for RawBroadcastPipeOut {
+    fn drop(&mut self) {
+        // the final flush waits for the live readers. It fails only after
+        // all readers are gone, then nobody loses the data.
+        if !self.buf.is_empty() {
+            let a = std::mem::take(&mut self.buf);
+            let _ = self.hub.send(a);
+        }
+        self.hub.close();
+    }
+}
+impl Write for RawBroadcastPipeOut {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // auto flush
+        if self.buf.len() >= BUF_SZ {
+            self.flush()?;
+        }
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let a = std::mem::take(&mut self.buf);
+        if let Err((a, err)) = self.hub.send(a) {
+            // keep the data as it was
+            self.buf = a;
+            return Err(err);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct RawLineBroadcastPipeOut {
+    buf: Vec<String>,
+    hub: Arc<Hub<Vec<String>>>,
+    // the bytes of a partial line by Write
+    partial: Vec<u8>,
+}
+impl RawLineBroadcastPipeOut {
+    fn new(hub: Arc<Hub<Vec<String>>>) -> Self {
+        Self {
+            buf: Vec::new(),
+            hub,
+            partial: Vec::new(),
+        }
+    }
+    fn take_partial(&mut self) -> Result<String> {
+        let v = std::mem::take(&mut self.partial);
+        String::from_utf8(v).map_err(|_| invalid_utf8())
+    }
+    fn push_line(&mut self, string: String) -> Result<()> {
+        self.buf.push(string);
+        if self.buf.len() > MSG_CHUNK_SZ {
+            self.flush_line()?;
+        }
+        Ok(())
+    }
+    fn write_line(&mut self, string: String) -> Result<()> {
+        if self.partial.is_empty() {
+            return self.push_line(string);
+        }
+        // the string ends the partial line
+        let mut line = self.take_partial()?;
+        line.push_str(&string);
+        self.push_line(line)
+    }
+    fn write_lines(&mut self, lines: Vec<String>) -> Result<()> {
+        for string in lines {
+            self.write_line(string)?;
+        }
+        Ok(())
+    }
+    fn flush_line(&mut self) -> Result<()> {
+        if !self.partial.is_empty() {
+            // push the trailing partial line
+            let line = self.take_partial()?;
+            self.buf.push(line);
+        }
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let a = std::mem::take(&mut self.buf);
+        if let Err((a, err)) = self.hub.send(a) {
+            // keep the data as it was
+            self.buf = a;
+            return Err(err);
+        }
+        Ok(())
+    }
+}
+impl Drop for RawLineBroadcastPipeOut {
+    fn drop(&mut self) {
+        // the final flush waits for the live readers. It fails only after
+        // all readers are gone, then nobody loses the data.
+        if !self.partial.is_empty() {
+            if let Ok(line) = self.take_partial() {
+                self.buf.push(line);
+            }
+        }
+        if !self.buf.is_empty() {
+            let a = std::mem::take(&mut self.buf);
+            let _ = self.hub.send(a);
+        }
+        self.hub.close();
+    }
+}
+impl Write for RawLineBroadcastPipeOut {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut rest = buf;
+        while let Some(idx) = rest.iter().position(|&b| b == b'\n') {
+            self.partial.extend_from_slice(&rest[..idx]);
+            let line = self.take_partial()?;
+            self.push_line(line)?;
+            rest = &rest[(idx + 1)..];
+        }
+        self.partial.extend_from_slice(rest);
+        Ok(buf.len())
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.flush_line()
+    }
+}
+
+//----------------------------------------------------------------------
+/// The chunks shared by the writer and the readers.
+#[derive(Debug)]
+struct Hub<T> {
+    inner: Mutex<RawHub<T>>,
+    readable: Condvar,
+    writable: Condvar,
+}
+
+/// `base` is the sequence number of the front chunk.
+#[derive(Debug)]
+struct RawHub<T> {
+    chunks: VecDeque<Arc<T>>,
+    base: u64,
+    subs: Vec<Option<Cursor>>,
+    cap: usize,
+    policy: BroadcastPolicy,
+    closed: bool,
+}
+
+/// The position of a reader.
+#[derive(Debug, Clone, Copy)]
+struct Cursor {
+    next: u64,
+    dropped: u64,
+}
+
+impl<T> Hub<T> {
+    fn new(cap: usize, policy: BroadcastPolicy) -> Self {
+        Self {
+            inner: Mutex::new(RawHub {
+                chunks: VecDeque::new(),
+                base: 0,
+                subs: Vec::new(),
+                cap: cap.max(1),
+                policy,
+                closed: false,
+            }),
+            readable: Condvar::new(),
+            writable: Condvar::new(),
+        }
+    }
+    fn lock(&self) -> MutexGuard<'_, RawHub<T>> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    fn subscribe(&self) -> usize {
+        let mut a = self.lock();
+        let cursor = Cursor {
+            next: a.base + a.chunks.len() as u64,
+            dropped: 0,
+        };
+        match a.subs.iter().position(|b| b.is_none()) {
+            Some(id) => {
+                a.subs[id] = Some(cursor);
+                id
+            }
+            None => {
+                a.subs.push(Some(cursor));
+                a.subs.len() - 1
+            }
+        }
+    }
+    fn unsubscribe(&self, id: usize) {
+        let mut a = self.lock();
+        a.subs[id] = None;
+        a.trim();
+        self.writable.notify_all();
+    }
+    /// send `chunk` to all readers, waits while they are slow.
+    /// On error, `chunk` is returned with the error.
+    fn send(&self, chunk: T) -> std::result::Result<(), (T, std::io::Error)> {
+        let mut a = self.lock();
+        loop {
+            if a.subs.iter().all(|b| b.is_none()) {
+                return Err((chunk, no_reader()));
+            }
+            if a.policy == BroadcastPolicy::LagAndDrop || a.chunks.len() < a.cap {
+                break;
+            }
+            a = self.writable.wait(a).unwrap_or_else(|e| e.into_inner());
+        }
+        a.chunks.push_back(Arc::new(chunk));
+        while a.chunks.len() > a.cap {
+            // the lagging readers lose it
+            a.chunks.pop_front();
+            a.base += 1;
+        }
+        self.readable.notify_all();
+        Ok(())
+    }
+    fn recv(&self, id: usize) -> Option<Arc<T>> {
+        let mut a = self.lock();
+        loop {
+            let base = a.base;
+            let end = base + a.chunks.len() as u64;
+            let cursor = a.subs[id].as_mut().unwrap();
+            if cursor.next < base {
+                cursor.dropped += base - cursor.next;
+                cursor.next = base;
+            }
+            if cursor.next < end {
+                let idx = (cursor.next - base) as usize;
+                cursor.next += 1;
+                let chunk = a.chunks[idx].clone();
+                if idx == 0 && a.trim() {
+                    self.writable.notify_all();
+                }
+                return Some(chunk);
+            }
+            if a.closed {
+                return None;
+            }
+            a = self.readable.wait(a).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+    fn dropped_chunks(&self, id: usize) -> u64 {
+        self.lock().subs[id].map(|a| a.dropped).unwrap_or(0)
+    }
+    fn close(&self) {
+        self.lock().closed = true;
+        self.readable.notify_all();
+    }
+}
+impl<T> RawHub<T> {
+    /// drop the chunks that all readers have read. returns true if dropped.
+    fn trim(&mut self) -> bool {
+        let end = self.base + self.chunks.len() as u64;
+        let min = self.subs.iter().flatten().map(|a| a.next).min();
+        let min = min.unwrap_or(end).min(end);
+        let mut r = false;
+        while self.base < min {
+            self.chunks.pop_front();
+            self.base += 1;
+            r = true;
+        }
+        r
+    }
+}
+
+/// A reader of `Hub`. It is unsubscribed on drop.
+#[derive(Debug)]
+struct Subscription<T> {
+    hub: Arc<Hub<T>>,
+    id: usize,
+}
+impl<T> Subscription<T> {
+    fn new(hub: &Arc<Hub<T>>) -> Self {
+        Self {
+            id: hub.subscribe(),
+            hub: hub.clone(),
+        }
+    }
+    #[inline(always)]
+    fn recv(&self) -> Option<Arc<T>> {
+        self.hub.recv(self.id)
+    }
+    #[inline(always)]
+    fn dropped_chunks(&self) -> u64 {
+        self.hub.dropped_chunks(self.id)
+    }
+}
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        self.hub.unsubscribe(self.id);
+    }
+}
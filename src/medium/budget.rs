@@ -0,0 +1,70 @@
+//!
+//! The byte budget of the data in flight, for the byte-bounded pipes.
+//!
+use super::wait::Wait;
+use std::sync::{Condvar, Mutex};
+use std::time::Instant;
+
+#[derive(Debug)]
+pub(crate) struct Budget {
+    max: usize,
+    inner: Mutex<RawBudget>,
+    cond: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct RawBudget {
+    used: usize,
+    // the reader is gone, then nothing is released
+    closed: bool,
+}
+
+impl Budget {
+    pub(crate) fn new(max: usize) -> Self {
+        Self {
+            max,
+            inner: Mutex::new(RawBudget::default()),
+            cond: Condvar::new(),
+        }
+    }
+    /// take `n` bytes from the budget, waits by `wait` until it is available.
+    ///
+    /// A chunk larger than the budget is taken when nothing is in flight.
+    /// After the reader is gone, this does not wait.
+    pub(crate) fn acquire(&self, n: usize, wait: Wait) -> std::io::Result<()> {
+        let mut a = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if a.closed || a.used == 0 || a.used + n <= self.max {
+                a.used += n;
+                return Ok(());
+            }
+            a = match wait {
+                Wait::Block => self.cond.wait(a).unwrap_or_else(|e| e.into_inner()),
+                Wait::Try => return Err(super::wait::would_block()),
+                Wait::Until(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(super::wait::timed_out());
+                    }
+                    let (a, _) = self
+                        .cond
+                        .wait_timeout(a, deadline - now)
+                        .unwrap_or_else(|e| e.into_inner());
+                    a
+                }
+            };
+        }
+    }
+    /// give back `n` bytes to the budget.
+    pub(crate) fn release(&self, n: usize) {
+        let mut a = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        a.used = a.used.saturating_sub(n);
+        self.cond.notify_all();
+    }
+    /// the reader is gone, wake up the waiting writers.
+    pub(crate) fn close(&self) {
+        let mut a = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        a.closed = true;
+        self.cond.notify_all();
+    }
+}
@@ -0,0 +1,226 @@
+//!
+//! The chain stream. This reads several input streams one after another,
+//! like `cat a b c`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use runnel::medium::chainio::ChainIn;
+//! use runnel::medium::stringio::StringIn;
+//! use runnel::*;
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//! use std::sync::Arc;
+//!
+//! let ended = Arc::new(AtomicUsize::new(0));
+//! let ended_c = ended.clone();
+//! let sin = ChainIn::with_boundary(
+//!     vec![
+//!         Box::new(StringIn::with_str("ABCDE\n")),
+//!         Box::new(StringIn::with_str("efgh\n")),
+//!     ],
+//!     move |_idx| {
+//!         ended_c.fetch_add(1, Ordering::SeqCst);
+//!     },
+//! );
+//! let mut lines_iter = sin.lines().map(|l| l.unwrap());
+//! assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+//! assert_eq!(sin.current(), 0);
+//! assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+//! assert_eq!(sin.current(), 1);
+//! assert_eq!(ended.load(Ordering::SeqCst), 1);
+//! assert_eq!(lines_iter.next(), None);
+//! assert_eq!(ended.load(Ordering::SeqCst), 2);
+//! ```
+//!
+use crate::*;
+
+use std::io::{BufRead, Read};
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+/// The callback that is called with the index of the source that has ended.
+pub type BoundaryFn = dyn Fn(usize) + Send + Sync + UnwindSafe + RefUnwindSafe;
+
+//----------------------------------------------------------------------
+//{{{ impl StreamIn
+/// The chain input stream.
+#[derive(Debug)]
+pub struct ChainIn(LockableChainIn);
+impl ChainIn {
+    pub fn with(sources: Vec<Box<dyn StreamIn>>) -> Self {
+        Self(LockableChainIn::with(sources, None))
+    }
+    /// create with `sources`, and `f` is called when each source ends.
+    ///
+    /// The argument of `f` is the index of the source that has ended.
+    /// The next source begins after that.
+    pub fn with_boundary<F>(sources: Vec<Box<dyn StreamIn>>, f: F) -> Self
+    where
+        F: Fn(usize) + Send + Sync + UnwindSafe + RefUnwindSafe + 'static,
+    {
+        Self(LockableChainIn::with(sources, Some(Box::new(f))))
+    }
+    /// Returns the index of the active source.
+    ///
+    /// This is the number of the sources after all of them have ended.
+    pub fn current(&self) -> usize {
+        self.0.idx.load(Ordering::SeqCst)
+    }
+    /// Returns the number of the sources.
+    pub fn len(&self) -> usize {
+        self.0.sources.len()
+    }
+    /// Returns true if there are no sources.
+    pub fn is_empty(&self) -> bool {
+        self.0.sources.is_empty()
+    }
+}
+impl StreamIn for ChainIn {
+    fn lock_bufread(&self) -> Box<dyn BufRead + '_> {
+        Box::new(ChainInLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn lines(&self) -> Box<dyn NextLine + '_> {
+        Box::new(Lines {
+            buf: self.0.lock(),
+            cur: None,
+        })
+    }
+}
+
+/// A locked reference to `ChainIn`
+pub struct ChainInLock<'a>(LockableChainInLock<'a>);
+impl Read for ChainInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+impl BufRead for ChainInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.0.fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.0.consume(amt)
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+struct LockableChainIn {
+    sources: Vec<Box<dyn StreamIn>>,
+    idx: AtomicUsize,
+    on_boundary: Option<Box<BoundaryFn>>,
+    inner: Mutex<()>,
+}
+impl LockableChainIn {
+    fn with(sources: Vec<Box<dyn StreamIn>>, on_boundary: Option<Box<BoundaryFn>>) -> Self {
+        LockableChainIn {
+            sources,
+            idx: AtomicUsize::new(0),
+            on_boundary,
+            inner: Mutex::new(()),
+        }
+    }
+    pub fn lock(&self) -> LockableChainInLock<'_> {
+        LockableChainInLock {
+            _guard: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+            chain: self,
+            cur: None,
+        }
+    }
+    /// the active source has ended, and the next source begins.
+    fn advance(&self) {
+        let idx = self.idx.load(Ordering::SeqCst);
+        if idx < self.sources.len() {
+            self.idx.store(idx + 1, Ordering::SeqCst);
+            if let Some(f) = self.on_boundary.as_ref() {
+                f(idx);
+            }
+        }
+    }
+    fn active(&self) -> Option<&dyn StreamIn> {
+        self.sources
+            .get(self.idx.load(Ordering::SeqCst))
+            .map(|a| a.as_ref())
+    }
+}
+impl std::fmt::Debug for LockableChainIn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LockableChainIn")
+            .field("sources", &self.sources)
+            .field("idx", &self.idx)
+            .field("on_boundary", &self.on_boundary.is_some())
+            .finish()
+    }
+}
+
+struct LockableChainInLock<'a> {
+    _guard: MutexGuard<'a, ()>,
+    chain: &'a LockableChainIn,
+    cur: Option<Box<dyn BufRead + 'a>>,
+}
+impl Read for LockableChainInLock<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = {
+            let src = self.fill_buf()?;
+            let len = src.len().min(buf.len());
+            buf[..len].copy_from_slice(&src[..len]);
+            len
+        };
+        self.consume(len);
+        Ok(len)
+    }
+}
+impl BufRead for LockableChainInLock<'_> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        loop {
+            if self.cur.is_none() {
+                match self.chain.active() {
+                    Some(a) => self.cur = Some(a.lock_bufread()),
+                    None => return Ok(&[]),
+                }
+            }
+            if !self.cur.as_mut().unwrap().fill_buf()?.is_empty() {
+                break;
+            }
+            self.cur = None;
+            self.chain.advance();
+        }
+        self.cur.as_mut().unwrap().fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        if let Some(a) = self.cur.as_mut() {
+            a.consume(amt)
+        }
+    }
+}
+
+pub struct Lines<'a> {
+    buf: LockableChainInLock<'a>,
+    cur: Option<Box<dyn NextLine + 'a>>,
+}
+impl Iterator for Lines<'_> {
+    type Item = Result<String>;
+    fn next(&mut self) -> Option<Result<String>> {
+        loop {
+            if self.cur.is_none() {
+                self.cur = Some(self.buf.chain.active()?.lines());
+            }
+            match self.cur.as_mut().unwrap().next() {
+                Some(r) => return Some(r),
+                None => {
+                    self.cur = None;
+                    self.buf.chain.advance();
+                }
+            }
+        }
+    }
+}
+impl NextLine for Lines<'_> {}
@@ -0,0 +1,463 @@
+//!
+//! The child process stream. This bridges [`std::process::Command`].
+//!
+//! The child's stdout becomes a [`ChildIn`], and the child's stdin becomes
+//! a [`ChildOut`]. The child's stderr is inherited, or it can be routed
+//! into a [`StreamErr`] by [`spawn_with_err()`].
+//!
+//! The exit status is returned by `wait()`, or by `exit_status()` after
+//! the child has exited. When all the streams are dropped, the child is
+//! reaped if it has exited; a running child is neither waited for nor
+//! killed, call `wait()` before dropping to reap it.
+//!
+//! [`StreamErr`]: crate::StreamErr
+//!
+use crate::*;
+
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::thread::JoinHandle;
+
+//----------------------------------------------------------------------
+/// spawn `cmd` and return ([`ChildOut`], [`ChildIn`]).
+///
+/// The child's stderr is inherited from the current process.
+///
+/// [`ChildOut`]: ChildOut
+/// [`ChildIn`]: ChildIn
+///
+pub fn spawn(cmd: &mut Command) -> Result<(ChildOut, ChildIn)> {
+    let child = cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+    Ok(with_child(child, None))
+}
+
+/// spawn `cmd` and return ([`ChildOut`], [`ChildIn`]).
+///
+/// The child's stderr is routed into `err` line by line on a working thread.
+///
+/// [`ChildOut`]: ChildOut
+/// [`ChildIn`]: ChildIn
+///
+pub fn spawn_with_err(cmd: &mut Command, err: Arc<dyn StreamErr>) -> Result<(ChildOut, ChildIn)> {
+    #[rustfmt::skip]
+    let child = cmd.stdin(Stdio::piped()).stdout(Stdio::piped())
+        .stderr(Stdio::piped()).spawn()?;
+    Ok(with_child(child, Some(err)))
+}
+
+/// spawn `cmd` and return [`ChildErr`], that writes the child's stdin.
+///
+/// This routes the error output into a command, like a logger.
+/// The child's stdout and stderr are inherited from the current process.
+///
+/// [`ChildErr`]: ChildErr
+///
+pub fn spawn_err(cmd: &mut Command) -> Result<ChildErr> {
+    let mut child = cmd.stdin(Stdio::piped()).spawn()?;
+    let stdin = child.stdin.take().unwrap();
+    let state = Arc::new(ChildState::new(child, None));
+    Ok(ChildErr(LockableChildOut::with(stdin, state)))
+}
+
+fn with_child(mut child: Child, err: Option<Arc<dyn StreamErr>>) -> (ChildOut, ChildIn) {
+    let stdin = child.stdin.take().unwrap();
+    let stdout = child.stdout.take().unwrap();
+    let err_handler = match (err, child.stderr.take()) {
+        (Some(err), Some(stderr)) => Some(std::thread::spawn(move || {
+            for line in BufReader::new(stderr).split(b'\n') {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                let _ = err.write_line(String::from_utf8_lossy(&line).to_string());
+            }
+            let _ = err.flush_line();
+        })),
+        _ => None,
+    };
+    let state = Arc::new(ChildState::new(child, err_handler));
+    (
+        ChildOut::with(stdin, state.clone()),
+        ChildIn::with(stdout, state),
+    )
+}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamIn
+/// The child process input stream. This reads the child's stdout.
+#[derive(Debug)]
+pub struct ChildIn(LockableChildIn);
+impl ChildIn {
+    fn with(a: ChildStdout, state: Arc<ChildState>) -> Self {
+        Self(LockableChildIn::with(a, state))
+    }
+    /// Returns the exit status of the child, if it has been collected.
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        self.0.state.exit_status()
+    }
+    /// Waits for the child to exit and returns the exit status.
+    ///
+    /// The child's stdin should be closed before, or this may block forever.
+    pub fn wait(&self) -> Result<ExitStatus> {
+        self.0.state.wait()
+    }
+}
+impl StreamIn for ChildIn {
+    fn lock_bufread(&self) -> Box<dyn BufRead + '_> {
+        Box::new(ChildInLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn lines(&self) -> Box<dyn NextLine + '_> {
+        let a = self.0.inner.lock().unwrap().take().unwrap();
+        let b = a.lines();
+        Box::new(Lines {
+            buf: b,
+            _state: self.0.state.clone(),
+        })
+    }
+}
+
+/// A locked reference to `ChildIn`
+#[derive(Debug)]
+pub struct ChildInLock<'a>(LockableChildInLock<'a>);
+impl Read for ChildInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+impl BufRead for ChildInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.0.fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.0.consume(amt)
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamOut
+/// The child process output stream. This writes the child's stdin.
+#[derive(Debug)]
+pub struct ChildOut(LockableChildOut);
+impl ChildOut {
+    fn with(a: ChildStdin, state: Arc<ChildState>) -> Self {
+        Self(LockableChildOut::with(a, state))
+    }
+    /// Returns the exit status of the child, if it has been collected.
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        self.0.state.exit_status()
+    }
+    /// Flushes and closes the child's stdin, the child sees EOF.
+    pub fn close(&self) -> Result<()> {
+        self.0.close()
+    }
+    /// Closes the child's stdin, waits for the child to exit and returns
+    /// the exit status.
+    pub fn wait(&self) -> Result<ExitStatus> {
+        self.0.close()?;
+        self.0.state.wait()
+    }
+}
+impl StreamOut for ChildOut {
+    fn lock(&self) -> Box<dyn StreamOutLock + '_> {
+        Box::new(ChildOutLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn write_line(&self, string: String) -> Result<()> {
+        self.lock().write_fmt(format_args!("{}\n", string))
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        write_lines_to(&mut *self.lock(), lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        write_lines_to(&mut *self.lock(), [string])
+    }
+    fn flush_line(&self) -> Result<()> {
+        self.lock().flush()
+    }
+}
+
+/// A locked reference to `ChildOut`
+#[derive(Debug)]
+pub struct ChildOutLock<'a>(LockableChildOutLock<'a>);
+impl StreamOutLock for ChildOutLock<'_> {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        self.0.buffer()
+    }
+}
+impl Write for ChildOutLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamErr
+/// The child process error stream. The error output is written to the
+/// child's stdin, see [`spawn_err()`].
+#[derive(Debug)]
+pub struct ChildErr(LockableChildOut);
+impl ChildErr {
+    /// Returns the exit status of the child, if it has been collected.
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        self.0.state.exit_status()
+    }
+    /// Flushes and closes the child's stdin, the child sees EOF.
+    pub fn close(&self) -> Result<()> {
+        self.0.close()
+    }
+    /// Closes the child's stdin, waits for the child to exit and returns
+    /// the exit status.
+    pub fn wait(&self) -> Result<ExitStatus> {
+        self.0.close()?;
+        self.0.state.wait()
+    }
+}
+impl StreamErr for ChildErr {
+    fn lock(&self) -> Box<dyn StreamErrLock + '_> {
+        Box::new(ChildErrLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn write_line(&self, string: String) -> Result<()> {
+        self.lock().write_fmt(format_args!("{}\n", string))
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        write_lines_to(&mut *self.lock(), lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        write_lines_to(&mut *self.lock(), [string])
+    }
+    fn flush_line(&self) -> Result<()> {
+        self.lock().flush()
+    }
+}
+
+impl std::convert::From<ChildOut> for ChildErr {
+    #[inline(always)]
+    fn from(a: ChildOut) -> Self {
+        Self(a.0)
+    }
+}
+
+/// A locked reference to `ChildErr`
+#[derive(Debug)]
+pub struct ChildErrLock<'a>(LockableChildOutLock<'a>);
+impl StreamErrLock for ChildErrLock<'_> {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        self.0.buffer()
+    }
+}
+impl Write for ChildErrLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+const LINE_BUF_SIZE: usize = 8 * 1024;
+
+#[derive(Debug)]
+struct ChildState {
+    child: Mutex<Child>,
+    status: Mutex<Option<ExitStatus>>,
+    err_handler: Mutex<Option<JoinHandle<()>>>,
+}
+impl ChildState {
+    fn new(child: Child, err_handler: Option<JoinHandle<()>>) -> Self {
+        Self {
+            child: Mutex::new(child),
+            status: Mutex::new(None),
+            err_handler: Mutex::new(err_handler),
+        }
+    }
+    /// collect the exit status without blocking, if the child has exited.
+    fn exit_status(&self) -> Option<ExitStatus> {
+        let mut status = self.status.lock().unwrap_or_else(|e| e.into_inner());
+        if status.is_none() {
+            // the child is locked while `wait()` blocks, it is not collected yet
+            let child = match self.child.try_lock() {
+                Ok(a) => Some(a),
+                Err(TryLockError::Poisoned(e)) => Some(e.into_inner()),
+                Err(TryLockError::WouldBlock) => None,
+            };
+            if let Some(mut child) = child {
+                if let Ok(Some(st)) = child.try_wait() {
+                    *status = Some(st);
+                }
+            }
+        }
+        *status
+    }
+    fn wait(&self) -> Result<ExitStatus> {
+        if let Some(st) = *self.status.lock().unwrap_or_else(|e| e.into_inner()) {
+            return Ok(st);
+        }
+        // the status is not locked while waiting, `exit_status()` does not block
+        let mut child = self.child.lock().unwrap_or_else(|e| e.into_inner());
+        let st = child.wait()?;
+        let handler = self
+            .err_handler
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        if let Some(handler) = handler {
+            let _ = handler.join();
+        }
+        *self.status.lock().unwrap_or_else(|e| e.into_inner()) = Some(st);
+        Ok(st)
+    }
+}
+impl Drop for ChildState {
+    fn drop(&mut self) {
+        // all the streams are dropped, the child's stdin and stdout are
+        // closed. reap the child if it has exited, a running child does
+        // not block the drop.
+        let _ = self.exit_status();
+    }
+}
+
+#[derive(Debug)]
+struct LockableChildIn {
+    inner: Mutex<Option<BufReader<ChildStdout>>>,
+    state: Arc<ChildState>,
+}
+impl LockableChildIn {
+    fn with(a: ChildStdout, state: Arc<ChildState>) -> Self {
+        LockableChildIn {
+            inner: Mutex::new(Some(BufReader::with_capacity(LINE_BUF_SIZE, a))),
+            state,
+        }
+    }
+    pub fn lock(&self) -> LockableChildInLock<'_> {
+        LockableChildInLock {
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+#[derive(Debug)]
+struct LockableChildInLock<'a> {
+    inner: MutexGuard<'a, Option<BufReader<ChildStdout>>>,
+}
+impl Read for LockableChildInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.as_mut().unwrap().read(buf)
+    }
+}
+impl BufRead for LockableChildInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.as_mut().unwrap().fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.inner.as_mut().unwrap().consume(amt)
+    }
+}
+
+#[derive(Debug)]
+struct LockableChildOut {
+    inner: Mutex<Option<BufWriter<ChildStdin>>>,
+    state: Arc<ChildState>,
+}
+impl LockableChildOut {
+    fn with(a: ChildStdin, state: Arc<ChildState>) -> Self {
+        LockableChildOut {
+            inner: Mutex::new(Some(BufWriter::with_capacity(LINE_BUF_SIZE, a))),
+            state,
+        }
+    }
+    pub fn lock(&self) -> LockableChildOutLock<'_> {
+        LockableChildOutLock {
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+    fn close(&self) -> Result<()> {
+        let a = self.inner.lock().unwrap_or_else(|e| e.into_inner()).take();
+        match a {
+            Some(mut a) => a.flush(),
+            None => Ok(()),
+        }
+    }
+}
+impl Drop for LockableChildOut {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+#[derive(Debug)]
+struct LockableChildOutLock<'a> {
+    inner: MutexGuard<'a, Option<BufWriter<ChildStdin>>>,
+}
+impl LockableChildOutLock<'_> {
+    #[inline(always)]
+    pub fn buffer(&self) -> &[u8] {
+        match self.inner.as_ref() {
+            Some(a) => a.buffer(),
+            None => b"",
+        }
+    }
+}
+impl Write for LockableChildOutLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self.inner.as_mut() {
+            Some(a) => a.write(buf),
+            None => Err(closed_error()),
+        }
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.inner.as_mut() {
+            Some(a) => a.flush(),
+            None => Err(closed_error()),
+        }
+    }
+}
+
+fn closed_error() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::BrokenPipe,
+        "the child's stdin is closed",
+    )
+}
+
+pub struct Lines {
+    buf: std::io::Lines<BufReader<ChildStdout>>,
+    // keep the child until the lines are dropped
+    _state: Arc<ChildState>,
+}
+impl Iterator for Lines {
+    type Item = Result<String>;
+    #[inline(always)]
+    fn next(&mut self) -> Option<Result<String>> {
+        self.buf.next()
+    }
+}
+impl NextLine for Lines {}
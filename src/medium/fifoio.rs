@@ -0,0 +1,512 @@
+//!
+//! The named fifo stream. This opens a fifo made by `mkfifo(3)`.
+//!
+//! Opening a fifo blocks until the peer opens it, like the shell does.
+//! The non-blocking open and the open with timeout are also available.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use runnel::medium::fifoio::{mkfifo, FifoIn, FifoOut};
+//! use runnel::*;
+//!
+//! let mut path = std::env::temp_dir();
+//! path.push(format!("runnel-doc-fifoio-{}", std::process::id()));
+//! mkfifo(&path, 0o600).unwrap();
+//!
+//! let path_w = path.clone();
+//! let handler = std::thread::spawn(move || {
+//!     let sout = FifoOut::open(&path_w).unwrap();
+//!     sout.write_line("ABCDE".to_string()).unwrap();
+//! });
+//! let sin = FifoIn::open(&path).unwrap();
+//! let mut lines_iter = sin.lines().map(|l| l.unwrap());
+//! assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+//! assert_eq!(lines_iter.next(), None);
+//! assert!(handler.join().is_ok());
+//! std::fs::remove_file(&path).unwrap();
+//! ```
+//!
+use crate::*;
+
+use std::ffi::CString;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::ops::ControlFlow;
+use std::os::raw::{c_char, c_int, c_short};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "dragonfly"
+))]
+type ModeT = u16;
+#[cfg(not(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "dragonfly"
+)))]
+type ModeT = u32;
+
+#[repr(C)]
+struct PollFd {
+    fd: c_int,
+    events: c_short,
+    revents: c_short,
+}
+
+extern "C" {
+    #[link_name = "mkfifo"]
+    fn c_mkfifo(path: *const c_char, mode: ModeT) -> c_int;
+    fn fcntl(fd: c_int, cmd: c_int, ...) -> c_int;
+    fn poll(fds: *mut PollFd, nfds: PollNfds, timeout: c_int) -> c_int;
+}
+const F_GETFL: c_int = 3;
+const F_SETFL: c_int = 4;
+// this module is compiled only on the targets that these are checked,
+// see `medium/mod.rs`.
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    not(any(target_arch = "mips", target_arch = "mips64"))
+))]
+const O_NONBLOCK: c_int = 0o4000;
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    any(target_arch = "mips", target_arch = "mips64")
+))]
+const O_NONBLOCK: c_int = 0x0080;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const O_NONBLOCK: c_int = 0x0004;
+const ENXIO: i32 = 6;
+const POLLIN: c_short = 0x0001;
+const POLLHUP: c_short = 0x0010;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+type PollNfds = std::os::raw::c_ulong;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+type PollNfds = std::os::raw::c_uint;
+
+const OPEN_RETRY_INTERVAL: Duration = Duration::from_millis(5);
+
+//----------------------------------------------------------------------
+/// make a named fifo at `path` with the permission `mode`.
+///
+/// This returns an error of `ErrorKind::AlreadyExists` if `path` exists.
+pub fn mkfifo<P: AsRef<Path>>(path: P, mode: u32) -> Result<()> {
+    let cpath = CString::new(path.as_ref().as_os_str().as_bytes())?;
+    if unsafe { c_mkfifo(cpath.as_ptr(), mode as ModeT) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn open_nonblocking(path: &Path, write: bool) -> Result<File> {
+    let r = OpenOptions::new()
+        .read(!write)
+        .write(write)
+        .custom_flags(O_NONBLOCK)
+        .open(path);
+    let file = match r {
+        Ok(file) => file,
+        Err(err) if err.raw_os_error() == Some(ENXIO) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::WouldBlock,
+                "no reader on the fifo",
+            ));
+        }
+        Err(err) => return Err(err),
+    };
+    // the i/o after open is blocking
+    let fd = file.as_raw_fd();
+    let flags = unsafe { fcntl(fd, F_GETFL) };
+    if flags == -1 || unsafe { fcntl(fd, F_SETFL, flags & !O_NONBLOCK) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(file)
+}
+
+fn timed_out_error() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::TimedOut, "no peer on the fifo")
+}
+
+fn open_write_timeout(path: &Path, timeout: Duration) -> Result<File> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match open_nonblocking(path, true) {
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {}
+            r => return r,
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(timed_out_error());
+        }
+        std::thread::sleep(OPEN_RETRY_INTERVAL.min(deadline - now));
+    }
+}
+
+fn open_read_timeout(path: &Path, timeout: Duration) -> Result<File> {
+    // a reader can not know whether a writer has opened without reading,
+    // so the blocking open is done on a working thread.
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let path_buf = path.to_path_buf();
+    let handler = std::thread::spawn(move || {
+        let _ = sender.send(File::open(path_buf));
+    });
+    let r = match receiver.recv_timeout(timeout) {
+        Ok(r) => r,
+        Err(_) => {
+            // unblock the pending open by opening the write side by ourself
+            loop {
+                let w = open_nonblocking(path, true);
+                match receiver.recv_timeout(OPEN_RETRY_INTERVAL) {
+                    Ok(r) => {
+                        drop(w);
+                        // a real writer may have opened in the meantime
+                        break match r {
+                            Ok(file) if has_writer(&file) => Ok(file),
+                            Ok(_) => Err(timed_out_error()),
+                            Err(err) => Err(err),
+                        };
+                    }
+                    Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                        break Err(timed_out_error())
+                    }
+                }
+            }
+        }
+    };
+    let _ = handler.join();
+    r
+}
+
+/// Returns true if a writer has the fifo open, or the data is in it.
+///
+/// After our own writer is closed, the read side reports `POLLHUP`
+/// without any other writer.
+fn has_writer(file: &File) -> bool {
+    let mut fds = PollFd {
+        fd: file.as_raw_fd(),
+        events: POLLIN,
+        revents: 0,
+    };
+    if unsafe { poll(&mut fds, 1, 0) } < 0 {
+        return false;
+    }
+    fds.revents & POLLIN != 0 || fds.revents & POLLHUP == 0
+}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamIn
+/// The named fifo input stream.
+#[derive(Debug)]
+pub struct FifoIn(LockableFifoIn);
+impl FifoIn {
+    pub fn with(a: File) -> Self {
+        Self(LockableFifoIn::with(a))
+    }
+    /// open the fifo at `path` for reading.
+    ///
+    /// This blocks until a writer opens the fifo.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::with(File::open(path)?))
+    }
+    /// open the fifo at `path` for reading without waiting for a writer.
+    ///
+    /// If no writer has opened the fifo, reading sees EOF immediately.
+    pub fn open_nonblocking<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::with(open_nonblocking(path.as_ref(), false)?))
+    }
+    /// open the fifo at `path` for reading, waiting for a writer up to `timeout`.
+    ///
+    /// This returns an error of `ErrorKind::TimedOut` on timeout.
+    pub fn open_timeout<P: AsRef<Path>>(path: P, timeout: Duration) -> Result<Self> {
+        Ok(Self::with(open_read_timeout(path.as_ref(), timeout)?))
+    }
+}
+impl StreamIn for FifoIn {
+    fn lock_bufread(&self) -> Box<dyn BufRead + '_> {
+        Box::new(FifoInLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn lines(&self) -> Box<dyn NextLine + '_> {
+        let a = self.0.inner.lock().unwrap().take().unwrap();
+        let b = a.lines();
+        Box::new(Lines { buf: b })
+    }
+    fn for_each_line(&self, f: &mut dyn FnMut(&str) -> Result<ControlFlow<()>>) -> Result<()> {
+        for_each_line_from(&mut FifoInLock(self.0.lock()), f)
+    }
+    fn read_line_into(&self, buf: &mut String) -> Result<bool> {
+        read_line_from(&mut FifoInLock(self.0.lock()), buf)
+    }
+}
+
+/// A locked reference to `FifoIn`
+#[derive(Debug)]
+pub struct FifoInLock<'a>(LockableFifoInLock<'a>);
+impl Read for FifoInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+impl BufRead for FifoInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.0.fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.0.consume(amt)
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamOut
+/// The named fifo output stream.
+#[derive(Debug)]
+pub struct FifoOut(LockableFifoOut);
+impl FifoOut {
+    pub fn with(a: File) -> Self {
+        Self(LockableFifoOut::with(a))
+    }
+    /// open the fifo at `path` for writing.
+    ///
+    /// This blocks until a reader opens the fifo.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::with(OpenOptions::new().write(true).open(path)?))
+    }
+    /// open the fifo at `path` for writing without waiting for a reader.
+    ///
+    /// If no reader has opened the fifo, this returns an error of
+    /// `ErrorKind::WouldBlock`.
+    pub fn open_nonblocking<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::with(open_nonblocking(path.as_ref(), true)?))
+    }
+    /// open the fifo at `path` for writing, waiting for a reader up to `timeout`.
+    ///
+    /// This returns an error of `ErrorKind::TimedOut` on timeout.
+    pub fn open_timeout<P: AsRef<Path>>(path: P, timeout: Duration) -> Result<Self> {
+        Ok(Self::with(open_write_timeout(path.as_ref(), timeout)?))
+    }
+}
+impl StreamOut for FifoOut {
+    fn lock(&self) -> Box<dyn StreamOutLock + '_> {
+        Box::new(FifoOutLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn write_line(&self, string: String) -> Result<()> {
+        self.lock().write_fmt(format_args!("{}\n", string))
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        write_lines_to(&mut *self.lock(), lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        write_lines_to(&mut *self.lock(), [string])
+    }
+    fn flush_line(&self) -> Result<()> {
+        self.lock().flush()
+    }
+}
+
+/// A locked reference to `FifoOut`
+#[derive(Debug)]
+pub struct FifoOutLock<'a>(LockableFifoOutLock<'a>);
+impl StreamOutLock for FifoOutLock<'_> {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        self.0.buffer()
+    }
+}
+impl Write for FifoOutLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamErr
+/// The named fifo error stream.
+#[derive(Debug)]
+pub struct FifoErr(LockableFifoOut);
+impl FifoErr {
+    pub fn with(a: File) -> Self {
+        Self(LockableFifoOut::with(a))
+    }
+    /// open the fifo at `path` for writing.
+    ///
+    /// This blocks until a reader opens the fifo.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::with(OpenOptions::new().write(true).open(path)?))
+    }
+    /// open the fifo at `path` for writing without waiting for a reader.
+    ///
+    /// If no reader has opened the fifo, this returns an error of
+    /// `ErrorKind::WouldBlock`.
+    pub fn open_nonblocking<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::with(open_nonblocking(path.as_ref(), true)?))
+    }
+    /// open the fifo at `path` for writing, waiting for a reader up to `timeout`.
+    ///
+    /// This returns an error of `ErrorKind::TimedOut` on timeout.
+    pub fn open_timeout<P: AsRef<Path>>(path: P, timeout: Duration) -> Result<Self> {
+        Ok(Self::with(open_write_timeout(path.as_ref(), timeout)?))
+    }
+}
+impl StreamErr for FifoErr {
+    fn lock(&self) -> Box<dyn StreamErrLock + '_> {
+        Box::new(FifoErrLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn write_line(&self, string: String) -> Result<()> {
+        self.lock().write_fmt(format_args!("{}\n", string))
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        write_lines_to(&mut *self.lock(), lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        write_lines_to(&mut *self.lock(), [string])
+    }
+    fn flush_line(&self) -> Result<()> {
+        self.lock().flush()
+    }
+}
+
+impl std::convert::From<FifoOut> for FifoErr {
+    #[inline(always)]
+    fn from(a: FifoOut) -> Self {
+        Self(a.0)
+    }
+}
+
+/// A locked reference to `FifoErr`
+#[derive(Debug)]
+pub struct FifoErrLock<'a>(LockableFifoOutLock<'a>);
+impl StreamErrLock for FifoErrLock<'_> {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        self.0.buffer()
+    }
+}
+impl Write for FifoErrLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+const LINE_BUF_SIZE: usize = 8 * 1024;
+
+#[derive(Debug)]
+struct LockableFifoIn {
+    inner: Mutex<Option<BufReader<File>>>,
+}
+impl LockableFifoIn {
+    pub fn with(a: File) -> Self {
+        LockableFifoIn {
+            inner: Mutex::new(Some(BufReader::with_capacity(LINE_BUF_SIZE, a))),
+        }
+    }
+    pub fn lock(&self) -> LockableFifoInLock<'_> {
+        LockableFifoInLock {
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LockableFifoInLock<'a> {
+    inner: MutexGuard<'a, Option<BufReader<File>>>,
+}
+impl Read for LockableFifoInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.as_mut().unwrap().read(buf)
+    }
+}
+impl BufRead for LockableFifoInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.as_mut().unwrap().fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.inner.as_mut().unwrap().consume(amt)
+    }
+}
+
+#[derive(Debug)]
+struct LockableFifoOut {
+    inner: Mutex<BufWriter<File>>,
+}
+impl LockableFifoOut {
+    fn with(a: File) -> Self {
+        LockableFifoOut {
+            inner: Mutex::new(BufWriter::with_capacity(LINE_BUF_SIZE, a)),
+        }
+    }
+    pub fn lock(&self) -> LockableFifoOutLock<'_> {
+        LockableFifoOutLock {
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LockableFifoOutLock<'a> {
+    inner: MutexGuard<'a, BufWriter<File>>,
+}
+impl LockableFifoOutLock<'_> {
+    #[inline(always)]
+    pub fn buffer(&self) -> &[u8] {
+        self.inner.buffer()
+    }
+}
+impl Write for LockableFifoOutLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.inner.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub struct Lines {
+    buf: std::io::Lines<BufReader<File>>,
+}
+impl Iterator for Lines {
+    type Item = Result<String>;
+    fn next(&mut self) -> Option<Result<String>> {
+        self.buf.next()
+    }
+}
+impl NextLine for Lines {}
@@ -0,0 +1,321 @@
+//!
+//! The file stream. This is thin-wrap of [`std::fs::File`].
+//!
+use crate::*;
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+//----------------------------------------------------------------------
+/// The open mode of the file output stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileMode {
+    /// create the file if it does not exist, and truncate it if it does.
+    Truncate,
+    /// create the file if it does not exist, and append to it if it does.
+    Append,
+    /// create a new file, failing if it already exists.
+    CreateNew,
+}
+impl FileMode {
+    fn open_options(&self) -> OpenOptions {
+        let mut opts = OpenOptions::new();
+        match self {
+            FileMode::Truncate => opts.write(true).create(true).truncate(true),
+            FileMode::Append => opts.append(true).create(true),
+            FileMode::CreateNew => opts.write(true).create_new(true),
+        };
+        opts
+    }
+}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamIn
+/// The file input stream.
+#[derive(Debug)]
+pub struct FileIn(LockableFileIn);
+impl FileIn {
+    pub fn with(a: File) -> Self {
+        Self(LockableFileIn::with(a))
+    }
+    /// open the file at `path` for reading.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::with(File::open(path)?))
+    }
+}
+impl StreamIn for FileIn {
+    fn lock_bufread(&self) -> Box<dyn BufRead + '_> {
+        Box::new(FileInLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn lines(&self) -> Box<dyn NextLine + '_> {
+        let a = self.0.inner.lock().unwrap().take().unwrap();
+        let b = a.lines();
+        Box::new(Lines { buf: b })
+    }
+    fn for_each_line(&self, f: &mut dyn FnMut(&str) -> Result<ControlFlow<()>>) -> Result<()> {
+        for_each_line_from(&mut FileInLock(self.0.lock()), f)
+    }
+    fn read_line_into(&self, buf: &mut String) -> Result<bool> {
+        read_line_from(&mut FileInLock(self.0.lock()), buf)
+    }
+}
+
+/// A locked reference to `FileIn`
+#[derive(Debug)]
+pub struct FileInLock<'a>(LockableFileInLock<'a>);
+impl Read for FileInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+impl BufRead for FileInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.0.fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.0.consume(amt)
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamOut
+/// The file output stream.
+#[derive(Debug)]
+pub struct FileOut(LockableFileOut);
+impl FileOut {
+    pub fn with(a: File) -> Self {
+        Self(LockableFileOut::with(a))
+    }
+    /// open the file at `path` for writing with `mode`.
+    pub fn open<P: AsRef<Path>>(path: P, mode: FileMode) -> Result<Self> {
+        Ok(Self::with(mode.open_options().open(path)?))
+    }
+    /// open the file at `path` with [`FileMode::Truncate`].
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open(path, FileMode::Truncate)
+    }
+    /// open the file at `path` with [`FileMode::Append`].
+    pub fn append<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open(path, FileMode::Append)
+    }
+    /// open the file at `path` with [`FileMode::CreateNew`].
+    pub fn create_new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open(path, FileMode::CreateNew)
+    }
+}
+impl StreamOut for FileOut {
+    fn lock(&self) -> Box<dyn StreamOutLock + '_> {
+        Box::new(FileOutLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn write_line(&self, string: String) -> Result<()> {
+        self.lock().write_fmt(format_args!("{}\n", string))
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        write_lines_to(&mut *self.lock(), lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        write_lines_to(&mut *self.lock(), [string])
+    }
+    fn flush_line(&self) -> Result<()> {
+        self.lock().flush()
+    }
+}
+
+/// A locked reference to `FileOut`
+#[derive(Debug)]
+pub struct FileOutLock<'a>(LockableFileOutLock<'a>);
+impl StreamOutLock for FileOutLock<'_> {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        self.0.buffer()
+    }
+}
+impl Write for FileOutLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamErr
+/// The file error stream.
+#[derive(Debug)]
+pub struct FileErr(LockableFileOut);
+impl FileErr {
+    pub fn with(a: File) -> Self {
+        Self(LockableFileOut::with(a))
+    }
+    /// open the file at `path` for writing with `mode`.
+    pub fn open<P: AsRef<Path>>(path: P, mode: FileMode) -> Result<Self> {
+        Ok(Self::with(mode.open_options().open(path)?))
+    }
+    /// open the file at `path` with [`FileMode::Truncate`].
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open(path, FileMode::Truncate)
+    }
+    /// open the file at `path` with [`FileMode::Append`].
+    pub fn append<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open(path, FileMode::Append)
+    }
+    /// open the file at `path` with [`FileMode::CreateNew`].
+    pub fn create_new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open(path, FileMode::CreateNew)
+    }
+}
+impl StreamErr for FileErr {
+    fn lock(&self) -> Box<dyn StreamErrLock + '_> {
+        Box::new(FileErrLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn write_line(&self, string: String) -> Result<()> {
+        self.lock().write_fmt(format_args!("{}\n", string))
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        write_lines_to(&mut *self.lock(), lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        write_lines_to(&mut *self.lock(), [string])
+    }
+    fn flush_line(&self) -> Result<()> {
+        self.lock().flush()
+    }
+}
+
+impl std::convert::From<FileOut> for FileErr {
+    #[inline(always)]
+    fn from(a: FileOut) -> Self {
+        Self(a.0)
+    }
+}
+
+/// A locked reference to `FileErr`
+#[derive(Debug)]
+pub struct FileErrLock<'a>(LockableFileOutLock<'a>);
+impl StreamErrLock for FileErrLock<'_> {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        self.0.buffer()
+    }
+}
+impl Write for FileErrLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+const LINE_BUF_SIZE: usize = 8 * 1024;
+
+#[derive(Debug)]
+struct LockableFileIn {
+    inner: Mutex<Option<BufReader<File>>>,
+}
+impl LockableFileIn {
+    pub fn with(a: File) -> Self {
+        LockableFileIn {
+            inner: Mutex::new(Some(BufReader::with_capacity(LINE_BUF_SIZE, a))),
+        }
+    }
+    pub fn lock(&self) -> LockableFileInLock<'_> {
+        LockableFileInLock {
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LockableFileInLock<'a> {
+    inner: MutexGuard<'a, Option<BufReader<File>>>,
+}
+impl Read for LockableFileInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.as_mut().unwrap().read(buf)
+    }
+}
+impl BufRead for LockableFileInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.as_mut().unwrap().fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.inner.as_mut().unwrap().consume(amt)
+    }
+}
+
+#[derive(Debug)]
+struct LockableFileOut {
+    inner: Mutex<BufWriter<File>>,
+}
+impl LockableFileOut {
+    fn with(a: File) -> Self {
+        LockableFileOut {
+            inner: Mutex::new(BufWriter::with_capacity(LINE_BUF_SIZE, a)),
+        }
+    }
+    pub fn lock(&self) -> LockableFileOutLock<'_> {
+        LockableFileOutLock {
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LockableFileOutLock<'a> {
+    inner: MutexGuard<'a, BufWriter<File>>,
+}
+impl LockableFileOutLock<'_> {
+    #[inline(always)]
+    pub fn buffer(&self) -> &[u8] {
+        self.inner.buffer()
+    }
+}
+impl Write for LockableFileOutLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.inner.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub struct Lines {
+    buf: std::io::Lines<BufReader<File>>,
+}
+impl Iterator for Lines {
+    type Item = Result<String>;
+    fn next(&mut self) -> Option<Result<String>> {
+        self.buf.next()
+    }
+}
+impl NextLine for Lines {}
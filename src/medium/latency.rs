@@ -0,0 +1,107 @@
+//!
+//! The max-latency flush of the pipe writers, by a background thread.
+//!
+//! One timer thread is shared by all the writers with the max latency,
+//! it is spawned by the first of them and lives until the process ends.
+//!
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::Thread;
+use std::time::{Duration, Instant};
+
+/// The writer that sends the data buffered too long.
+pub(crate) trait FlushAged {
+    /// the time when the oldest data in the buffer was written.
+    fn since(&self) -> Option<Instant>;
+    /// send the buffer without blocking, the timer thread is shared. On
+    /// error, the data is kept and the error is returned by the next call
+    /// of the writer.
+    fn flush_aged(&mut self);
+}
+
+type Entry = (Weak<Mutex<dyn FlushAged + Send>>, Duration);
+
+struct Timer {
+    entries: Vec<Entry>,
+    thread: Option<Thread>,
+}
+
+static TIMER: Mutex<Timer> = Mutex::new(Timer {
+    entries: Vec::new(),
+    thread: None,
+});
+
+fn lock_timer() -> std::sync::MutexGuard<'static, Timer> {
+    TIMER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// spawn the timer thread, if it is not yet.
+///
+/// Call this before [`register()`], the error of spawning is returned.
+pub(crate) fn start() -> std::io::Result<()> {
+    let mut timer = lock_timer();
+    if timer.thread.is_none() {
+        let handle = std::thread::Builder::new()
+            .name("runnel-flusher".to_string())
+            .spawn(run)?;
+        timer.thread = Some(handle.thread().clone());
+    }
+    Ok(())
+}
+
+/// register the writer that sends the data buffered longer than `latency`.
+///
+/// The writer is unregistered after it is dropped.
+pub(crate) fn register<T>(a: Weak<Mutex<T>>, latency: Duration)
+where
+    T: FlushAged + Send + 'static,
+{
+    let mut timer = lock_timer();
+    timer.entries.push((a, latency));
+    if let Some(thread) = timer.thread.as_ref() {
+        // recompute the time to wake up
+        thread.unpark();
+    }
+}
+
+fn run() {
+    loop {
+        let entries = {
+            let mut timer = lock_timer();
+            timer.entries.retain(|(a, _)| a.strong_count() > 0);
+            timer.entries.clone()
+        };
+        // the writers are locked without the timer lock
+        let next = entries
+            .into_iter()
+            .filter_map(|(a, latency)| a.upgrade().map(|a| flush_aged(&a, latency)))
+            .min();
+        match next {
+            Some(dur) => std::thread::park_timeout(dur),
+            None => std::thread::park(),
+        }
+    }
+}
+
+/// flush `a` if its data is aged, returns the time until the next check.
+///
+/// A panic in it does not end the thread, the other writers are flushed.
+fn flush_aged(a: &Arc<Mutex<dyn FlushAged + Send>>, latency: Duration) -> Duration {
+    let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| flush_aged_raw(a, latency)));
+    r.unwrap_or(latency)
+}
+
+fn flush_aged_raw(a: &Arc<Mutex<dyn FlushAged + Send>>, latency: Duration) -> Duration {
+    let mut a = a.lock().unwrap_or_else(|e| e.into_inner());
+    match a.since() {
+        Some(since) => {
+            let elapsed = since.elapsed();
+            if elapsed >= latency {
+                a.flush_aged();
+                latency
+            } else {
+                latency - elapsed
+            }
+        }
+        None => latency,
+    }
+}
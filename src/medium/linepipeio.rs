@@ -3,11 +3,49 @@
 //!
 //! This idea is to make line-by-line processing more efficient.
 //!
+//! The [`LinePipeIn::lock_bufread()`] presents the lines as
+//! a newline-terminated byte stream, for a filter that reads bytes.
+//!
+//! The [`LinePipeOut::lock()`] returns a writer that splits the bytes
+//! into lines. A partial line is buffered until its newline or a flush.
+//!
+//! The [`LinePipeOut`] and [`LinePipeErr`] can be cloned for many producers.
+//! Each clone has its own buffer, and the reader gets EOF after all clones
+//! are dropped or closed.
+//!
+//! The reads and writes wait without limit by default. For bounded waits,
+//! there are [`LinePipeIn::lines_timeout()`], [`LinePipeOut::try_write_line()`],
+//! [`LinePipeOut::write_timeout()`] and so on.
+//! They return `ErrorKind::WouldBlock` or `ErrorKind::TimedOut`.
+//!
+//! The [`LinePipeOut::close()`] flushes and signals EOF without dropping.
+//! A [`LinePipeOut`] that is dropped without `close()` flushes its buffer,
+//! and the error of it is given to [`LinePipeOut::on_drop_error()`]. The flush
+//! on drop waits for the reader while it is alive, the data is not lost.
+//! Writing to the pipe that the reader is gone or that is closed returns
+//! `ErrorKind::BrokenPipe`.
+//!
+//! The [`line_pipe()`] bounds the chunks in flight by the count.
+//! The [`line_pipe_bytes()`] bounds them by the total heap bytes of the strings.
+//!
+//! The sizes can be tuned by [`LinePipeBuilder`]. With [`LinePipeBuilder::max_latency()`],
+//! the buffered lines are sent after the duration even if the writer is idle.
+//!
+//! [`LinePipeIn::lock_bufread()`]: crate::StreamIn::lock_bufread
+//! [`LinePipeOut::lock()`]: crate::StreamOut::lock
+//!
+use super::budget::Budget;
+use super::latency::{self, FlushAged};
+use super::pipeio::DropErrorFn;
+use super::wait::{self, Sender, Wait};
 use crate::*;
 
 use std::io::{BufRead, Read, Write};
+use std::ops::ControlFlow;
+use std::panic::{RefUnwindSafe, UnwindSafe};
 use std::sync::mpsc::{Receiver, SyncSender};
-use std::sync::{Mutex, MutexGuard};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 
 //----------------------------------------------------------------------
 /// create in-memory fifo text line stream and return ([`LinePipeOut`], [`LinePipeIn`]).
@@ -17,16 +55,137 @@ use std::sync::{Mutex, MutexGuard};
 ///
 #[inline(always)]
 pub fn line_pipe(sz: usize) -> (LinePipeOut, LinePipeIn) {
-    let (sender, receiver) = std::sync::mpsc::sync_channel(sz);
-    (LinePipeOut::with(sender), LinePipeIn::with(receiver))
+    LinePipeBuilder::new().capacity(sz).build_pipe()
+}
+
+/// create in-memory fifo text line stream that is bounded by the bytes in flight,
+/// and return ([`LinePipeOut`], [`LinePipeIn`]).
+///
+/// The writer waits while the chunks in flight would exceed `bytes`.
+/// The bytes of a chunk is the sum of the heap sizes of its strings.
+///
+/// [`LinePipeOut`]: LinePipeOut
+/// [`LinePipeIn`]: LinePipeIn
+///
+#[inline(always)]
+pub fn line_pipe_bytes(bytes: usize) -> (LinePipeOut, LinePipeIn) {
+    LinePipeBuilder::new().capacity_bytes(bytes).build_pipe()
+}
+
+/// The builder of the in-memory fifo text line stream.
+///
+/// ```rust
+/// use runnel::medium::linepipeio::LinePipeBuilder;
+///
+/// // an interactive stage, that sends each line soon
+/// let (sout, sin) = LinePipeBuilder::new()
+///     .chunk_lines(1)
+///     .capacity(64)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinePipeBuilder {
+    capacity: usize,
+    chunk_lines: usize,
+    max_latency: Option<Duration>,
+    capacity_bytes: Option<usize>,
+}
+impl LinePipeBuilder {
+    /// create builder
+    pub fn new() -> Self {
+        Self {
+            capacity: 1,
+            chunk_lines: MSG_CHUNK_SZ,
+            max_latency: None,
+            capacity_bytes: None,
+        }
+    }
+    /// set the number of the chunks in the channel. The default is 1.
+    pub fn capacity(mut self, sz: usize) -> Self {
+        self.capacity = sz;
+        self
+    }
+    /// set the total bytes of the chunks in the channel, instead of the count.
+    ///
+    /// The bytes of a chunk is the sum of the heap sizes of its strings.
+    /// A chunk that is larger than `bytes` is sent when the channel is empty.
+    pub fn capacity_bytes(mut self, bytes: usize) -> Self {
+        self.capacity_bytes = Some(bytes);
+        self
+    }
+    /// set the lines of a chunk. The default is 1024.
+    ///
+    /// The writer sends its lines as a chunk, when it holds this or more.
+    pub fn chunk_lines(mut self, sz: usize) -> Self {
+        self.chunk_lines = sz.max(1);
+        self
+    }
+    /// set the max latency. The default is none.
+    ///
+    /// The lines buffered longer than `dur` are sent by a background thread,
+    /// even if the chunk is not full. The thread is shared by all the
+    /// writers, it does not wait for a full pipe, and retries later.
+    /// A partial line written by bytes is not sent until its newline.
+    pub fn max_latency(mut self, dur: Duration) -> Self {
+        self.max_latency = Some(dur);
+        self
+    }
+    /// build to ([`LinePipeOut`], [`LinePipeIn`])
+    ///
+    /// With the max latency, it fails if the background thread can not be
+    /// spawned.
+    pub fn build(self) -> Result<(LinePipeOut, LinePipeIn)> {
+        if self.max_latency.is_some() {
+            latency::start()?;
+        }
+        Ok(self.build_pipe())
+    }
+    /// build without the max latency check, it does not fail.
+    fn build_pipe(self) -> (LinePipeOut, LinePipeIn) {
+        let (sender, receiver, budget) = match self.capacity_bytes {
+            Some(bytes) => {
+                let (sender, receiver) = std::sync::mpsc::channel();
+                let budget = Arc::new(Budget::new(bytes));
+                (Sender::Unbounded(sender), receiver, Some(budget))
+            }
+            None => {
+                let (sender, receiver) = std::sync::mpsc::sync_channel(self.capacity);
+                (Sender::Bounded(sender), receiver, None)
+            }
+        };
+        let mut raw_out = RawLinePipeOut::with_sender(sender);
+        raw_out.chunk_sz = self.chunk_lines;
+        raw_out.latency = self.max_latency;
+        raw_out.budget = budget.clone();
+        let mut raw_in = RawLinePipeIn::new(receiver);
+        raw_in.budget = budget;
+        (
+            LinePipeOut(LockableLinePipeOut::with(raw_out)),
+            LinePipeIn(LockableLinePipeIn::with_raw(raw_in)),
+        )
+    }
+}
+impl Default for LinePipeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 trait WriteString {
     fn write_line(&mut self, string: String) -> Result<()>;
+    fn write_lines(&mut self, lines: Vec<String>) -> Result<()>;
     fn flush_line(&mut self) -> Result<()>;
 }
 
-const MSG_CHUNK_SZ: usize = 2 * 512;
+pub(crate) const MSG_CHUNK_SZ: usize = 2 * 512;
+
+/// the heap bytes of the chunk, for the byte-bounded pipe. It counts the
+/// strings, and the vector of them.
+fn heap_bytes(a: &Vec<String>) -> usize {
+    let strings: usize = a.iter().map(|s| s.capacity()).sum();
+    strings + a.capacity() * std::mem::size_of::<String>()
+}
 
 //----------------------------------------------------------------------
 //{{{ impl StreamIn
@@ -37,11 +196,25 @@ impl LinePipeIn {
     pub fn with(a: Receiver<Vec<String>>) -> Self {
         Self(LockableLinePipeIn::with(a))
     }
+    /// Returns a locked reference, that has the non-blocking reads.
+    pub fn lock(&self) -> LinePipeInLock<'_> {
+        LinePipeInLock(self.0.lock())
+    }
+    /// Returns an iterator over the lines, each line waits at most `dur`.
+    ///
+    /// On timeout, the iterator returns `ErrorKind::TimedOut`, and it can be
+    /// continued without losing lines.
+    pub fn lines_timeout(&self, dur: Duration) -> LinesTimeout<'_> {
+        LinesTimeout {
+            buf: self.0.lock(),
+            dur,
+        }
+    }
 }
 impl StreamIn for LinePipeIn {
     #[inline(always)]
     fn lock_bufread(&self) -> Box<dyn BufRead + '_> {
-        unimplemented!()
+        Box::new(LinePipeInLock(self.0.lock()))
     }
     #[inline(always)]
     fn is_line_pipe(&self) -> bool {
@@ -51,28 +224,60 @@ impl StreamIn for LinePipeIn {
         let a = self.0.inner.lock().unwrap().take().unwrap();
         Box::new(Lines { buf: a })
     }
+    fn for_each_line(&self, f: &mut dyn FnMut(&str) -> Result<ControlFlow<()>>) -> Result<()> {
+        let mut a = self.0.lock();
+        // lend the received strings, without copying
+        while let Some(r) = a.next_with(Wait::Block) {
+            if f(&r?)?.is_break() {
+                break;
+            }
+        }
+        Ok(())
+    }
+    fn read_line_into(&self, buf: &mut String) -> Result<bool> {
+        match self.0.lock().next_with(Wait::Block) {
+            Some(Ok(line)) => {
+                // copy into `buf`, to reuse its capacity
+                buf.clear();
+                buf.push_str(&line);
+                Ok(true)
+            }
+            Some(Err(err)) => Err(err),
+            None => {
+                buf.clear();
+                Ok(false)
+            }
+        }
+    }
 }
 
 /// A locked reference to `LinePipeIn`
-#[allow(dead_code)]
+#[derive(Debug)]
 pub struct LinePipeInLock<'a>(LockableLinePipeInLock<'a>);
+impl LinePipeInLock<'_> {
+    /// like `fill_buf()`, but if no line is ready, returns `ErrorKind::WouldBlock`.
+    pub fn try_fill_buf(&mut self) -> Result<&[u8]> {
+        self.0.fill_buf_with(Wait::Try)
+    }
+    /// like `fill_buf()`, but waits at most `dur`, or returns `ErrorKind::TimedOut`.
+    pub fn fill_buf_timeout(&mut self, dur: Duration) -> Result<&[u8]> {
+        self.0.fill_buf_with(Wait::timeout(dur))
+    }
+}
 impl Read for LinePipeInLock<'_> {
     #[inline(always)]
-    fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
-        //self.0.read(buf)
-        unimplemented!()
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
     }
 }
 impl BufRead for LinePipeInLock<'_> {
     #[inline(always)]
     fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
-        //self.0.fill_buf()
-        unimplemented!()
+        self.0.fill_buf()
     }
     #[inline(always)]
-    fn consume(&mut self, _amt: usize) {
-        //self.0.consume(amt)
-        unimplemented!()
+    fn consume(&mut self, amt: usize) {
+        self.0.consume(amt)
     }
 }
 //}}}
@@ -87,11 +292,60 @@ impl LinePipeOut {
         Self(LockableLinePipeOut::with(RawLinePipeOut::with(sender)))
     }
 }
+impl Clone for LinePipeOut {
+    /// The clone has its own empty buffer, and shares the channel.
+    fn clone(&self) -> Self {
+        Self(self.0.clone_empty())
+    }
+}
+impl LinePipeOut {
+    /// set `f` that is called when the final flush on drop fails.
+    ///
+    /// The buffered data is flushed when the last reference is dropped.
+    /// The error of it can not be returned, so `f` is called with it.
+    /// The clones made after this share `f`.
+    pub fn on_drop_error<F>(self, f: F) -> Self
+    where
+        F: Fn(&std::io::Error) + Send + Sync + UnwindSafe + RefUnwindSafe + 'static,
+    {
+        self.0.lock().inner.on_drop_error = Some(Arc::new(f));
+        self
+    }
+    /// like `write_line()`, but if it has to wait, returns `ErrorKind::WouldBlock`.
+    ///
+    /// The `string` is written only when this returns `Ok`.
+    pub fn try_write_line(&self, string: String) -> Result<()> {
+        self.0.lock().inner.write_line_with(string, Wait::Try)
+    }
+    /// like `write_line()`, but waits at most `dur`, or returns `ErrorKind::TimedOut`.
+    ///
+    /// The `string` is written only when this returns `Ok`.
+    pub fn write_timeout(&self, string: String, dur: Duration) -> Result<()> {
+        self.0
+            .lock()
+            .inner
+            .write_line_with(string, Wait::timeout(dur))
+    }
+    /// like `flush_line()`, but if it has to wait, returns `ErrorKind::WouldBlock`.
+    pub fn try_flush_line(&self) -> Result<()> {
+        self.0.lock().inner.flush_with(Wait::Try)
+    }
+    /// like `flush_line()`, but waits at most `dur`, or returns `ErrorKind::TimedOut`.
+    pub fn flush_timeout(&self, dur: Duration) -> Result<()> {
+        self.0.lock().inner.flush_with(Wait::timeout(dur))
+    }
+    /// flush the buffer and signal EOF, without dropping this.
+    ///
+    /// The later writes return `ErrorKind::BrokenPipe`. With the clones,
+    /// the reader gets EOF after all of them are closed or dropped.
+    pub fn close(&self) -> Result<()> {
+        self.0.lock().inner.close()
+    }
+}
 impl StreamOut for LinePipeOut {
     #[inline(always)]
     fn lock(&self) -> Box<dyn StreamOutLock + '_> {
-        unimplemented!()
-        //Box::new(LinePipeOutLock(self.0.lock()))
+        Box::new(LinePipeOutLock(self.0.lock()))
     }
     #[inline(always)]
     fn is_line_pipe(&self) -> bool {
@@ -100,6 +354,9 @@ impl StreamOut for LinePipeOut {
     fn write_line(&self, string: String) -> Result<()> {
         self.0.lock().write_line(string)
     }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        self.0.lock().write_lines(lines)
+    }
     fn flush_line(&self) -> Result<()> {
         self.0.lock().flush_line()
     }
@@ -111,17 +368,17 @@ pub struct LinePipeOutLock<'a>(LockableLinePipeOutLock<'a>);
 impl StreamOutLock for LinePipeOutLock<'_> {
     #[inline(always)]
     fn buffer(&self) -> &[u8] {
-        unimplemented!()
+        self.0.buffer()
     }
 }
 impl Write for LinePipeOutLock<'_> {
     #[inline(always)]
-    fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
-        unimplemented!()
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
     }
     #[inline(always)]
     fn flush(&mut self) -> std::io::Result<()> {
-        unimplemented!()
+        self.0.flush()
     }
 }
 impl WriteString for LinePipeOutLock<'_> {
@@ -130,6 +387,10 @@ impl WriteString for LinePipeOutLock<'_> {
         self.0.write_line(string)
     }
     #[inline(always)]
+    fn write_lines(&mut self, lines: Vec<String>) -> std::io::Result<()> {
+        self.0.write_lines(lines)
+    }
+    #[inline(always)]
     fn flush_line(&mut self) -> std::io::Result<()> {
         self.0.flush_line()
     }
@@ -146,10 +407,60 @@ impl LinePipeErr {
         Self(LockableLinePipeOut::with(RawLinePipeOut::with(sender)))
     }
 }
+impl Clone for LinePipeErr {
+    /// The clone has its own empty buffer, and shares the channel.
+    fn clone(&self) -> Self {
+        Self(self.0.clone_empty())
+    }
+}
+impl LinePipeErr {
+    /// set `f` that is called when the final flush on drop fails.
+    ///
+    /// The buffered data is flushed when the last reference is dropped.
+    /// The error of it can not be returned, so `f` is called with it.
+    /// The clones made after this share `f`.
+    pub fn on_drop_error<F>(self, f: F) -> Self
+    where
+        F: Fn(&std::io::Error) + Send + Sync + UnwindSafe + RefUnwindSafe + 'static,
+    {
+        self.0.lock().inner.on_drop_error = Some(Arc::new(f));
+        self
+    }
+    /// like `write_line()`, but if it has to wait, returns `ErrorKind::WouldBlock`.
+    ///
+    /// The `string` is written only when this returns `Ok`.
+    pub fn try_write_line(&self, string: String) -> Result<()> {
+        self.0.lock().inner.write_line_with(string, Wait::Try)
+    }
+    /// like `write_line()`, but waits at most `dur`, or returns `ErrorKind::TimedOut`.
+    ///
+    /// The `string` is written only when this returns `Ok`.
+    pub fn write_timeout(&self, string: String, dur: Duration) -> Result<()> {
+        self.0
+            .lock()
+            .inner
+            .write_line_with(string, Wait::timeout(dur))
+    }
+    /// like `flush_line()`, but if it has to wait, returns `ErrorKind::WouldBlock`.
+    pub fn try_flush_line(&self) -> Result<()> {
+        self.0.lock().inner.flush_with(Wait::Try)
+    }
+    /// like `flush_line()`, but waits at most `dur`, or returns `ErrorKind::TimedOut`.
+    pub fn flush_timeout(&self, dur: Duration) -> Result<()> {
+        self.0.lock().inner.flush_with(Wait::timeout(dur))
+    }
+    /// flush the buffer and signal EOF, without dropping this.
+    ///
+    /// The later writes return `ErrorKind::BrokenPipe`. With the clones,
+    /// the reader gets EOF after all of them are closed or dropped.
+    pub fn close(&self) -> Result<()> {
+        self.0.lock().inner.close()
+    }
+}
 impl StreamErr for LinePipeErr {
     #[inline(always)]
     fn lock(&self) -> Box<dyn StreamErrLock + '_> {
-        unimplemented!()
+        Box::new(LinePipeErrLock(self.0.lock()))
     }
     #[inline(always)]
     fn is_line_pipe(&self) -> bool {
@@ -158,6 +469,9 @@ impl StreamErr for LinePipeErr {
     fn write_line(&self, string: String) -> Result<()> {
         self.0.lock().write_line(string)
     }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        self.0.lock().write_lines(lines)
+    }
     fn flush_line(&self) -> Result<()> {
         self.0.lock().flush_line()
     }
@@ -171,23 +485,22 @@ impl std::convert::From<LinePipeOut> for LinePipeErr {
 }
 
 /// A locked reference to `LinePipeErr`
-#[allow(dead_code)]
 #[derive(Debug)]
 pub struct LinePipeErrLock<'a>(LockableLinePipeOutLock<'a>);
 impl StreamErrLock for LinePipeErrLock<'_> {
     #[inline(always)]
     fn buffer(&self) -> &[u8] {
-        unimplemented!()
+        self.0.buffer()
     }
 }
 impl Write for LinePipeErrLock<'_> {
     #[inline(always)]
-    fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
-        unimplemented!()
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
     }
     #[inline(always)]
     fn flush(&mut self) -> std::io::Result<()> {
-        unimplemented!()
+        self.0.flush()
     }
 }
 //}}}
@@ -200,44 +513,75 @@ struct LockableLinePipeIn {
 }
 impl LockableLinePipeIn {
     pub fn with(a: Receiver<Vec<String>>) -> Self {
+        Self::with_raw(RawLinePipeIn::new(a))
+    }
+    fn with_raw(a: RawLinePipeIn) -> Self {
         LockableLinePipeIn {
-            inner: Mutex::new(Some(RawLinePipeIn::new(a))),
+            inner: Mutex::new(Some(a)),
+        }
+    }
+    pub fn lock(&self) -> LockableLinePipeInLock<'_> {
+        LockableLinePipeInLock {
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
         }
     }
 }
 
 #[derive(Debug)]
 struct LockableLinePipeInLock<'a> {
-    _inner: MutexGuard<'a, Option<RawLinePipeIn>>,
+    inner: MutexGuard<'a, Option<RawLinePipeIn>>,
+}
+impl LockableLinePipeInLock<'_> {
+    fn fill_buf_with(&mut self, wait: Wait) -> Result<&[u8]> {
+        let a = self.inner.as_mut().unwrap();
+        a.wait = wait;
+        let r = a.fill_buf().map(|b| b.len());
+        a.wait = Wait::Block;
+        r?;
+        // this returns the buffered bytes without receiving
+        a.fill_buf()
+    }
+    fn next_with(&mut self, wait: Wait) -> Option<Result<String>> {
+        let a = self.inner.as_mut().unwrap();
+        a.wait = wait;
+        let r = a.next();
+        a.wait = Wait::Block;
+        r
+    }
 }
 impl Read for LockableLinePipeInLock<'_> {
     #[inline(always)]
-    fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
-        unimplemented!()
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.as_mut().unwrap().read(buf)
     }
 }
 impl BufRead for LockableLinePipeInLock<'_> {
     #[inline(always)]
     fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
-        //self.inner.as_mut().unwrap().fill_buf()
-        unimplemented!()
+        self.inner.as_mut().unwrap().fill_buf()
     }
     #[inline(always)]
-    fn consume(&mut self, _amt: usize) {
-        //self.inner.as_mut().unwrap().consume(amt)
-        unimplemented!()
+    fn consume(&mut self, amt: usize) {
+        self.inner.as_mut().unwrap().consume(amt)
     }
 }
 
 #[derive(Debug)]
 struct LockableLinePipeOut {
-    inner: Mutex<RawLinePipeOut>,
+    inner: Arc<Mutex<RawLinePipeOut>>,
 }
 impl LockableLinePipeOut {
     fn with(a: RawLinePipeOut) -> Self {
-        LockableLinePipeOut {
-            inner: Mutex::new(a),
+        let latency = a.latency;
+        let inner = Arc::new(Mutex::new(a));
+        if let Some(dur) = latency {
+            // the timer thread is started by the builder
+            latency::register(Arc::downgrade(&inner), dur);
         }
+        LockableLinePipeOut { inner }
+    }
+    fn clone_empty(&self) -> Self {
+        Self::with(self.lock().inner.clone_empty())
     }
     pub fn lock(&self) -> LockableLinePipeOutLock<'_> {
         LockableLinePipeOutLock {
@@ -245,19 +589,30 @@ impl LockableLinePipeOut {
         }
     }
 }
-
+impl Drop for LockableLinePipeOut {
+    fn drop(&mut self) {
+        // flush here, the flusher thread may hold the last `Arc` a moment
+        self.lock().inner.flush_on_drop();
+    }
+}
 #[derive(Debug)]
 struct LockableLinePipeOutLock<'a> {
     inner: MutexGuard<'a, RawLinePipeOut>,
 }
+impl LockableLinePipeOutLock<'_> {
+    #[inline(always)]
+    pub fn buffer(&self) -> &[u8] {
+        self.inner.partial.as_slice()
+    }
+}
 impl Write for LockableLinePipeOutLock<'_> {
     #[inline(always)]
-    fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
-        unimplemented!()
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.inner.write(buf)
     }
     #[inline(always)]
     fn flush(&mut self) -> std::io::Result<()> {
-        unimplemented!()
+        self.inner.flush()
     }
 }
 impl WriteString for LockableLinePipeOutLock<'_> {
@@ -266,6 +621,10 @@ impl WriteString for LockableLinePipeOutLock<'_> {
         self.inner.write_line(string)
     }
     #[inline(always)]
+    fn write_lines(&mut self, lines: Vec<String>) -> std::io::Result<()> {
+        self.inner.write_lines(lines)
+    }
+    #[inline(always)]
     fn flush_line(&mut self) -> std::io::Result<()> {
         self.inner.flush_line()
     }
@@ -282,86 +641,360 @@ impl Iterator for Lines {
 }
 impl NextLine for Lines {}
 
+/// An iterator over the lines with a timeout. See [`LinePipeIn::lines_timeout()`].
+pub struct LinesTimeout<'a> {
+    buf: LockableLinePipeInLock<'a>,
+    dur: Duration,
+}
+impl Iterator for LinesTimeout<'_> {
+    type Item = Result<String>;
+    #[inline(always)]
+    fn next(&mut self) -> Option<Result<String>> {
+        self.buf.next_with(Wait::timeout(self.dur))
+    }
+}
+
 #[derive(Debug)]
 struct RawLinePipeIn {
     buf: Vec<String>,
     receiver: Receiver<Vec<String>>,
+    // the bytes of the current line with '\n', for BufRead
+    bytes: Vec<u8>,
+    pos: usize,
+    wait: Wait,
+    budget: Option<Arc<Budget>>,
 }
 impl RawLinePipeIn {
     fn new(a: Receiver<Vec<String>>) -> Self {
         Self {
             buf: Vec::with_capacity(MSG_CHUNK_SZ),
             receiver: a,
+            bytes: Vec::new(),
+            pos: 0,
+            wait: Wait::Block,
+            budget: None,
         }
     }
     fn next(&mut self) -> Option<Result<String>> {
-        if self.buf.is_empty() {
-            let mut b = match self.receiver.recv() {
-                Ok(s) => s,
-                Err(_) => return None,
+        if self.pos < self.bytes.len() {
+            // the rest of the line that is partially read as bytes
+            return Some(self.take_rest_bytes());
+        }
+        self.next_line()
+    }
+    fn take_rest_bytes(&mut self) -> Result<String> {
+        let mut v = self.bytes.split_off(self.pos);
+        self.bytes.clear();
+        self.pos = 0;
+        if v.last() == Some(&b'\n') {
+            v.pop();
+        }
+        String::from_utf8(v).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )
+        })
+    }
+    fn next_line(&mut self) -> Option<Result<String>> {
+        // skip the empty chunks of flush_line() without lines
+        while self.buf.is_empty() {
+            let mut b = match self.wait.recv(&self.receiver) {
+                Ok(Some(s)) => s,
+                Ok(None) => return None,
+                Err(err) => return Some(Err(err)),
             };
+            if let Some(budget) = self.budget.as_ref() {
+                budget.release(heap_bytes(&b));
+            }
             b.reverse();
             self.buf = b;
         }
         Some(Ok(self.buf.pop().unwrap()))
     }
 }
+impl Drop for RawLinePipeIn {
+    fn drop(&mut self) {
+        if let Some(budget) = self.budget.as_ref() {
+            // wake up the writers waiting for the budget
+            budget.close();
+        }
+    }
+}
 impl Read for RawLinePipeIn {
-    #[inline(always)]
-    fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
-        unimplemented!();
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = {
+            let src = self.fill_buf()?;
+            let len = src.len().min(buf.len());
+            buf[..len].copy_from_slice(&src[..len]);
+            len
+        };
+        self.consume(len);
+        Ok(len)
     }
 }
 impl BufRead for RawLinePipeIn {
-    #[inline(always)]
     fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
-        unimplemented!();
+        if self.pos >= self.bytes.len() {
+            self.bytes.clear();
+            self.pos = 0;
+            if let Some(r) = self.next_line() {
+                let line = r?;
+                self.bytes.extend_from_slice(line.as_bytes());
+                self.bytes.push(b'\n');
+            }
+        }
+        Ok(&self.bytes[self.pos..])
     }
     #[inline(always)]
-    fn consume(&mut self, _amt: usize) {
-        unimplemented!();
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.bytes.len());
     }
 }
 
-#[derive(Debug)]
 struct RawLinePipeOut {
     buf: Vec<String>,
-    sender: SyncSender<Vec<String>>,
+    // `None` after closed
+    sender: Option<Sender<Vec<String>>>,
+    // the bytes of a partial line by Write
+    partial: Vec<u8>,
+    on_drop_error: Option<Arc<DropErrorFn>>,
+    chunk_sz: usize,
+    latency: Option<Duration>,
+    // the time when the buffer became non-empty, with the latency
+    since: Option<Instant>,
+    // shared with the reader, for the byte-bounded pipe
+    budget: Option<Arc<Budget>>,
 }
 impl RawLinePipeOut {
     pub fn with(a: SyncSender<Vec<String>>) -> Self {
+        Self::with_sender(Sender::Bounded(a))
+    }
+    fn with_sender(a: Sender<Vec<String>>) -> Self {
+        Self {
+            buf: Vec::new(),
+            sender: Some(a),
+            partial: Vec::new(),
+            on_drop_error: None,
+            chunk_sz: MSG_CHUNK_SZ,
+            latency: None,
+            since: None,
+            budget: None,
+        }
+    }
+    fn clone_empty(&self) -> Self {
         Self {
             buf: Vec::new(),
-            sender: a,
+            sender: self.sender.clone(),
+            partial: Vec::new(),
+            on_drop_error: self.on_drop_error.clone(),
+            chunk_sz: self.chunk_sz,
+            latency: self.latency,
+            since: None,
+            budget: self.budget.clone(),
+        }
+    }
+    fn take_partial(&mut self) -> Result<String> {
+        let v = std::mem::take(&mut self.partial);
+        String::from_utf8(v).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )
+        })
+    }
+    fn push_line(&mut self, string: String) -> Result<()> {
+        if self.sender.is_none() {
+            return Err(wait::closed());
+        }
+        self.buf.push(string);
+        self.touch();
+        if self.buf.len() >= self.chunk_sz {
+            self.flush_line()?;
+        }
+        Ok(())
+    }
+    /// note the time of the oldest line, for the max latency.
+    #[inline(always)]
+    fn touch(&mut self) {
+        if self.latency.is_some() && self.since.is_none() {
+            self.since = Some(Instant::now());
+        }
+    }
+    fn write_line_with(&mut self, string: String, wait: Wait) -> Result<()> {
+        if wait == Wait::Block {
+            return self.write_line(string);
+        }
+        if self.sender.is_none() {
+            return Err(wait::closed());
+        }
+        // send the full chunk first, so that the string is kept only on success
+        if self.buf.len() >= self.chunk_sz {
+            self.send_with(wait)?;
+        }
+        let line = if self.partial.is_empty() {
+            string
+        } else {
+            let mut line = self.take_partial()?;
+            line.push_str(&string);
+            line
+        };
+        self.buf.push(line);
+        self.touch();
+        if self.buf.len() >= self.chunk_sz {
+            // if the pipe is not ready, the lines are kept and the error is
+            // returned by the next call
+            match self.send_with(wait) {
+                Err(err) if !wait::is_not_ready(&err) => return Err(err),
+                _ => (),
+            }
         }
+        Ok(())
+    }
+    fn flush_with(&mut self, wait: Wait) -> Result<()> {
+        if !self.partial.is_empty() {
+            // push the trailing partial line
+            let line = self.take_partial()?;
+            self.buf.push(line);
+        }
+        self.send_with(wait)
+    }
+    fn send_with(&mut self, wait: Wait) -> Result<()> {
+        let sender = match self.sender.as_ref() {
+            Some(sender) => sender,
+            None => return Err(wait::closed()),
+        };
+        let mut v = Vec::with_capacity(self.buf.len());
+        v.append(&mut self.buf); // move String instance
+        let bytes = match self.budget.as_ref() {
+            Some(budget) => {
+                // the same chunk is released by the reader
+                let bytes = heap_bytes(&v);
+                if let Err(err) = budget.acquire(bytes, wait) {
+                    // keep the lines as they were
+                    self.buf = v;
+                    return Err(err);
+                }
+                bytes
+            }
+            None => 0,
+        };
+        if let Err((v, err)) = wait.send(sender, v) {
+            if let Some(budget) = self.budget.as_ref() {
+                budget.release(bytes);
+            }
+            // keep the lines as they were
+            self.buf = v;
+            return Err(err);
+        }
+        self.since = None;
+        Ok(())
+    }
+    /// flush and drop the sender. The reader gets EOF after all clones are closed.
+    fn close(&mut self) -> Result<()> {
+        let r = if self.sender.is_some() {
+            self.flush_with(Wait::Block)
+        } else {
+            Ok(())
+        };
+        self.sender = None;
+        r
     }
 }
 impl Write for RawLinePipeOut {
-    #[inline(always)]
-    fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
-        unimplemented!();
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut rest = buf;
+        while let Some(idx) = rest.iter().position(|&b| b == b'\n') {
+            self.partial.extend_from_slice(&rest[..idx]);
+            let line = self.take_partial()?;
+            self.push_line(line)?;
+            rest = &rest[(idx + 1)..];
+        }
+        self.partial.extend_from_slice(rest);
+        Ok(buf.len())
     }
     #[inline(always)]
     fn flush(&mut self) -> std::io::Result<()> {
-        unimplemented!();
+        self.flush_line()
+    }
+}
+impl FlushAged for RawLinePipeOut {
+    #[inline(always)]
+    fn since(&self) -> Option<Instant> {
+        self.since
+    }
+    fn flush_aged(&mut self) {
+        // the partial line is kept until its newline
+        let _ = self.send_with(Wait::Try);
+    }
+}
+impl RawLinePipeOut {
+    /// the final flush on drop, and drop the sender.
+    fn flush_on_drop(&mut self) {
+        if self.sender.is_none() || (self.buf.is_empty() && self.partial.is_empty()) {
+            self.sender = None;
+            return;
+        }
+        // wait for the reader, the send fails after the reader is gone
+        if let Err(err) = self.flush_with(Wait::Block) {
+            if let Some(f) = self.on_drop_error.as_ref() {
+                f(&err);
+            }
+        }
+        self.sender = None;
+    }
+}
+impl std::fmt::Debug for RawLinePipeOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RawLinePipeOut")
+            .field("buf", &self.buf)
+            .field("sender", &self.sender)
+            .field("partial", &self.partial)
+            .field("on_drop_error", &self.on_drop_error.is_some())
+            .field("chunk_sz", &self.chunk_sz)
+            .field("latency", &self.latency)
+            .field("since", &self.since)
+            .finish()
     }
 }
 impl WriteString for RawLinePipeOut {
     fn write_line(&mut self, string: String) -> Result<()> {
-        self.buf.push(string);
-        if self.buf.len() > MSG_CHUNK_SZ {
+        if self.partial.is_empty() {
+            return self.push_line(string);
+        }
+        // the string ends the partial line
+        let mut line = self.take_partial()?;
+        line.push_str(&string);
+        self.push_line(line)
+    }
+    fn write_lines(&mut self, lines: Vec<String>) -> Result<()> {
+        if self.sender.is_none() {
+            return Err(wait::closed());
+        }
+        if lines.is_empty() {
+            return Ok(());
+        }
+        let mut lines = lines;
+        if !self.partial.is_empty() {
+            // the first string ends the partial line
+            let mut line = self.take_partial()?;
+            line.push_str(&lines[0]);
+            lines[0] = line;
+        }
+        // move the whole vec, without pushing each string
+        if self.buf.is_empty() {
+            self.buf = lines;
+        } else {
+            self.buf.append(&mut lines);
+        }
+        self.touch();
+        if self.buf.len() >= self.chunk_sz {
             self.flush_line()?;
         }
         Ok(())
     }
+    #[inline(always)]
     fn flush_line(&mut self) -> Result<()> {
-        let mut v = Vec::with_capacity(self.buf.len());
-        v.append(&mut self.buf); // move String instance
-        let r = self.sender.send(v);
-        if let Err(err) = r {
-            return Err(std::io::Error::new(std::io::ErrorKind::Other, err));
-        }
-        self.buf.clear();
-        Ok(())
+        self.flush_with(Wait::Block)
     }
 }
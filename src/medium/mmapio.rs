@@ -0,0 +1,281 @@
+//!
+//! The memory-mapped file stream. This maps a file read-only by `mmap(2)`.
+//!
+//! The `fill_buf()` of [`MmapIn::lock_bufread()`] returns the whole rest of
+//! the mapped file as a single window, so that no copy is done through
+//! a small buffer.
+//!
+//! The file should not be truncated while it is mapped, or the process
+//! will get `SIGBUS`. A file that its size is not known by the metadata,
+//! like the files in `/proc`, is read into the memory instead.
+//!
+//! [`MmapIn::lock_bufread()`]: crate::StreamIn::lock_bufread
+//!
+use crate::*;
+
+use std::fs::File;
+use std::io::{BufRead, Read};
+use std::ops::ControlFlow;
+use std::os::raw::{c_int, c_void};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+extern "C" {
+    fn mmap(
+        addr: *mut c_void,
+        len: usize,
+        prot: c_int,
+        flags: c_int,
+        fd: c_int,
+        offset: OffT,
+    ) -> *mut c_void;
+    fn munmap(addr: *mut c_void, len: usize) -> c_int;
+}
+const PROT_READ: c_int = 1;
+const MAP_PRIVATE: c_int = 2;
+// `off_t` is `long` with glibc and bionic (without `_FILE_OFFSET_BITS=64`)
+// and on solaris; it is 64 bits with musl and on the BSDs and macos.
+#[cfg(any(
+    all(
+        any(target_os = "linux", target_os = "android"),
+        not(target_env = "musl")
+    ),
+    target_os = "solaris",
+    target_os = "illumos"
+))]
+type OffT = std::os::raw::c_long;
+#[cfg(not(any(
+    all(
+        any(target_os = "linux", target_os = "android"),
+        not(target_env = "musl")
+    ),
+    target_os = "solaris",
+    target_os = "illumos"
+)))]
+type OffT = i64;
+
+//----------------------------------------------------------------------
+//{{{ impl StreamIn
+/// The memory-mapped file input stream.
+#[derive(Debug)]
+pub struct MmapIn(LockableMmapIn);
+impl MmapIn {
+    /// map the whole file `a` read-only.
+    pub fn try_with(a: File) -> Result<Self> {
+        Ok(Self(LockableMmapIn::with(Mmap::map(a)?)))
+    }
+    /// open the file at `path` and map it read-only.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::try_with(File::open(path)?)
+    }
+}
+impl StreamIn for MmapIn {
+    fn lock_bufread(&self) -> Box<dyn BufRead + '_> {
+        Box::new(MmapInLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn lines(&self) -> Box<dyn NextLine + '_> {
+        Box::new(Lines { buf: self.0.lock() })
+    }
+    fn for_each_line(&self, f: &mut dyn FnMut(&str) -> Result<ControlFlow<()>>) -> Result<()> {
+        for_each_line_from(&mut MmapInLock(self.0.lock()), f)
+    }
+    fn read_line_into(&self, buf: &mut String) -> Result<bool> {
+        read_line_from(&mut MmapInLock(self.0.lock()), buf)
+    }
+}
+
+/// A locked reference to `MmapIn`
+#[derive(Debug)]
+pub struct MmapInLock<'a>(LockableMmapInLock<'a>);
+impl Read for MmapInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+impl BufRead for MmapInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.0.fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.0.consume(amt)
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+#[derive(Debug)]
+struct LockableMmapIn {
+    inner: Mutex<RawMmapIn>,
+}
+impl LockableMmapIn {
+    fn with(a: Mmap) -> Self {
+        LockableMmapIn {
+            inner: Mutex::new(RawMmapIn::new(a)),
+        }
+    }
+    pub fn lock(&self) -> LockableMmapInLock<'_> {
+        LockableMmapInLock {
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LockableMmapInLock<'a> {
+    inner: MutexGuard<'a, RawMmapIn>,
+}
+impl Read for LockableMmapInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+impl BufRead for LockableMmapInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt)
+    }
+}
+
+pub struct Lines<'a> {
+    buf: LockableMmapInLock<'a>,
+}
+impl Iterator for Lines<'_> {
+    type Item = Result<String>;
+    fn next(&mut self) -> Option<Result<String>> {
+        self.buf.inner.next_line()
+    }
+}
+impl NextLine for Lines<'_> {}
+
+#[derive(Debug)]
+struct RawMmapIn {
+    map: Mmap,
+    pos: usize,
+}
+impl RawMmapIn {
+    fn new(a: Mmap) -> Self {
+        Self { map: a, pos: 0 }
+    }
+    fn next_line(&mut self) -> Option<Result<String>> {
+        let src = &self.map.as_slice()[self.pos..];
+        if src.is_empty() {
+            return None;
+        }
+        let (line, amt) = match src.iter().position(|&b| b == b'\n') {
+            Some(idx) => (&src[..idx], idx + 1),
+            None => (src, src.len()),
+        };
+        let line = match line.last() {
+            Some(b'\r') if amt > line.len() => &line[..(line.len() - 1)],
+            _ => line,
+        };
+        let r = match std::str::from_utf8(line) {
+            Ok(s) => Ok(s.to_string()),
+            Err(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )),
+        };
+        self.pos += amt;
+        Some(r)
+    }
+}
+impl Read for RawMmapIn {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let src = &self.map.as_slice()[self.pos..];
+        let len = src.len().min(buf.len());
+        buf[..len].copy_from_slice(&src[..len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+impl BufRead for RawMmapIn {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        Ok(&self.map.as_slice()[self.pos..])
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.map.as_slice().len());
+    }
+}
+
+#[derive(Debug)]
+struct Mmap {
+    ptr: *const u8,
+    len: usize,
+    // the contents read, without the mapping
+    heap: Vec<u8>,
+}
+// the mapping is read-only, and it is owned by this.
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+impl Mmap {
+    fn map(mut file: File) -> Result<Self> {
+        let len = file.metadata()?.len();
+        if len > usize::MAX as u64 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the file is too large to map",
+            ));
+        }
+        let len = len as usize;
+        if len == 0 {
+            // mmap(2) can not map zero length, and the size of the files
+            // in `/proc` is zero. read it into the memory.
+            let mut heap = Vec::new();
+            file.read_to_end(&mut heap)?;
+            return Ok(Self {
+                ptr: std::ptr::null(),
+                len: 0,
+                heap,
+            });
+        }
+        let ptr = unsafe {
+            mmap(
+                std::ptr::null_mut(),
+                len,
+                PROT_READ,
+                MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr as isize == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Self {
+            ptr: ptr as *const u8,
+            len,
+            heap: Vec::new(),
+        })
+    }
+    #[inline(always)]
+    fn as_slice(&self) -> &[u8] {
+        if self.len == 0 {
+            return &self.heap;
+        }
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            unsafe {
+                munmap(self.ptr as *mut c_void, self.len);
+            }
+        }
+    }
+}
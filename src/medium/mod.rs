@@ -5,7 +5,49 @@
 //! [`StreamOut`]: crate::StreamOut
 //! [`StreamErr`]: crate::StreamErr
 //!
+pub mod broadcastio;
+mod budget;
+pub mod chainio;
+pub mod childio;
+// the targets that the value of `O_NONBLOCK` is checked.
+#[cfg(any(
+    all(
+        any(target_os = "linux", target_os = "android"),
+        any(
+            target_arch = "x86",
+            target_arch = "x86_64",
+            target_arch = "arm",
+            target_arch = "aarch64",
+            target_arch = "mips",
+            target_arch = "mips64",
+            target_arch = "powerpc",
+            target_arch = "powerpc64",
+            target_arch = "riscv64",
+            target_arch = "s390x",
+        )
+    ),
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly",
+))]
+pub mod fifoio;
+pub mod fileio;
+mod latency;
 pub mod linepipeio;
+#[cfg(unix)]
+pub mod mmapio;
+pub mod nullio;
+#[cfg(unix)]
+pub mod ospipeio;
 pub mod pipeio;
+pub mod ringpipeio;
 pub mod stdio;
 pub mod stringio;
+pub mod tcpio;
+pub mod teeio;
+#[cfg(unix)]
+pub mod unixsock;
+mod wait;
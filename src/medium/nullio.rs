@@ -0,0 +1,148 @@
+//!
+//! The null stream. This is like `/dev/null`.
+//!
+//! [`NullOut`] and [`NullErr`] discard everything written, and
+//! [`EmptyIn`] is at EOF from the beginning. They hold no buffer,
+//! so they are cheap for a long run.
+//!
+use crate::*;
+
+use std::io::{BufRead, Write};
+
+//----------------------------------------------------------------------
+//{{{ impl StreamIn
+/// The empty input stream. This is always at EOF.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EmptyIn;
+impl StreamIn for EmptyIn {
+    fn lock_bufread(&self) -> Box<dyn BufRead + '_> {
+        Box::new(std::io::empty())
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn lines(&self) -> Box<dyn NextLine + '_> {
+        Box::new(Lines)
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamOut
+/// The null output stream. This discards everything.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NullOut;
+impl StreamOut for NullOut {
+    fn lock(&self) -> Box<dyn StreamOutLock + '_> {
+        Box::new(NullOutLock(()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    #[inline(always)]
+    fn write_line(&self, _string: String) -> Result<()> {
+        Ok(())
+    }
+    #[inline(always)]
+    fn write_lines(&self, _lines: Vec<String>) -> Result<()> {
+        Ok(())
+    }
+    #[inline(always)]
+    fn write_str_line(&self, _string: &str) -> Result<()> {
+        Ok(())
+    }
+    #[inline(always)]
+    fn flush_line(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A locked reference to `NullOut`
+#[derive(Debug)]
+pub struct NullOutLock(());
+impl StreamOutLock for NullOutLock {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        b""
+    }
+}
+impl Write for NullOutLock {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(buf.len())
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamErr
+/// The null error stream. This discards everything.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NullErr;
+impl StreamErr for NullErr {
+    fn lock(&self) -> Box<dyn StreamErrLock + '_> {
+        Box::new(NullErrLock(()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    #[inline(always)]
+    fn write_line(&self, _string: String) -> Result<()> {
+        Ok(())
+    }
+    #[inline(always)]
+    fn write_lines(&self, _lines: Vec<String>) -> Result<()> {
+        Ok(())
+    }
+    #[inline(always)]
+    fn write_str_line(&self, _string: &str) -> Result<()> {
+        Ok(())
+    }
+    #[inline(always)]
+    fn flush_line(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl std::convert::From<NullOut> for NullErr {
+    #[inline(always)]
+    fn from(_a: NullOut) -> Self {
+        Self
+    }
+}
+
+/// A locked reference to `NullErr`
+#[derive(Debug)]
+pub struct NullErrLock(());
+impl StreamErrLock for NullErrLock {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        b""
+    }
+}
+impl Write for NullErrLock {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(buf.len())
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+pub struct Lines;
+impl Iterator for Lines {
+    type Item = Result<String>;
+    #[inline(always)]
+    fn next(&mut self) -> Option<Result<String>> {
+        None
+    }
+}
+impl NextLine for Lines {}
@@ -0,0 +1,474 @@
+//!
+//! The os pipe stream. This is backed by the kernel pipe file descriptors of `pipe(2)`.
+//!
+//! Unlike [`pipeio`], the ends can be handed to a child process as
+//! [`std::process::Stdio`], or across `fork` as [`OwnedFd`].
+//!
+//! [`pipeio`]: crate::medium::pipeio
+//!
+use crate::*;
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::ops::ControlFlow;
+use std::os::unix::io::OwnedFd;
+use std::process::Stdio;
+use std::sync::{Mutex, MutexGuard};
+
+//----------------------------------------------------------------------
+/// create os pipe and return ([`OsPipeOut`], [`OsPipeIn`]).
+///
+/// Both file descriptors are created with `FD_CLOEXEC`. On linux and the
+/// BSDs, this is atomic by `pipe2(2)`; on the other targets, it is set by
+/// `fcntl(2)` after `pipe(2)`, and a concurrent `fork` may leak the fds.
+///
+/// [`OsPipeOut`]: OsPipeOut
+/// [`OsPipeIn`]: OsPipeIn
+///
+pub fn os_pipe() -> Result<(OsPipeOut, OsPipeIn)> {
+    let (fd_in, fd_out) = sys::cloexec_pipe()?;
+    Ok((OsPipeOut::from(fd_out), OsPipeIn::from(fd_in)))
+}
+
+// the targets that the value of `O_CLOEXEC` is checked.
+#[cfg(any(
+    all(
+        any(target_os = "linux", target_os = "android"),
+        any(
+            target_arch = "x86",
+            target_arch = "x86_64",
+            target_arch = "arm",
+            target_arch = "aarch64",
+            target_arch = "mips",
+            target_arch = "mips64",
+            target_arch = "powerpc",
+            target_arch = "powerpc64",
+            target_arch = "riscv64",
+            target_arch = "s390x",
+        )
+    ),
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+))]
+mod sys {
+    use std::os::raw::c_int;
+    use std::os::unix::io::{FromRawFd, OwnedFd};
+
+    extern "C" {
+        fn pipe2(fds: *mut c_int, flags: c_int) -> c_int;
+    }
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const O_CLOEXEC: c_int = 0o2000000;
+    #[cfg(target_os = "freebsd")]
+    const O_CLOEXEC: c_int = 0x0010_0000;
+    #[cfg(target_os = "netbsd")]
+    const O_CLOEXEC: c_int = 0x0040_0000;
+    #[cfg(target_os = "openbsd")]
+    const O_CLOEXEC: c_int = 0x0001_0000;
+
+    pub(super) fn cloexec_pipe() -> std::io::Result<(OwnedFd, OwnedFd)> {
+        let mut fds: [c_int; 2] = [-1, -1];
+        if unsafe { pipe2(fds.as_mut_ptr(), O_CLOEXEC) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let fd_in = unsafe { OwnedFd::from_raw_fd(fds[0]) };
+        let fd_out = unsafe { OwnedFd::from_raw_fd(fds[1]) };
+        Ok((fd_in, fd_out))
+    }
+}
+
+#[cfg(not(any(
+    all(
+        any(target_os = "linux", target_os = "android"),
+        any(
+            target_arch = "x86",
+            target_arch = "x86_64",
+            target_arch = "arm",
+            target_arch = "aarch64",
+            target_arch = "mips",
+            target_arch = "mips64",
+            target_arch = "powerpc",
+            target_arch = "powerpc64",
+            target_arch = "riscv64",
+            target_arch = "s390x",
+        )
+    ),
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+)))]
+mod sys {
+    use std::os::raw::c_int;
+    use std::os::unix::io::{FromRawFd, OwnedFd};
+
+    extern "C" {
+        fn pipe(fds: *mut c_int) -> c_int;
+        fn fcntl(fd: c_int, cmd: c_int, ...) -> c_int;
+    }
+    const F_SETFD: c_int = 2;
+    const FD_CLOEXEC: c_int = 1;
+
+    pub(super) fn cloexec_pipe() -> std::io::Result<(OwnedFd, OwnedFd)> {
+        let mut fds: [c_int; 2] = [-1, -1];
+        if unsafe { pipe(fds.as_mut_ptr()) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // take the ownership at first, they are closed on error.
+        let fd_in = unsafe { OwnedFd::from_raw_fd(fds[0]) };
+        let fd_out = unsafe { OwnedFd::from_raw_fd(fds[1]) };
+        for fd in fds {
+            if unsafe { fcntl(fd, F_SETFD, FD_CLOEXEC) } == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok((fd_in, fd_out))
+    }
+}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamIn
+/// The os pipe input stream.
+#[derive(Debug)]
+pub struct OsPipeIn(LockableOsPipeIn);
+impl OsPipeIn {
+    pub fn with(a: File) -> Self {
+        Self(LockableOsPipeIn::with(a))
+    }
+}
+impl StreamIn for OsPipeIn {
+    fn lock_bufread(&self) -> Box<dyn BufRead + '_> {
+        Box::new(OsPipeInLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn lines(&self) -> Box<dyn NextLine + '_> {
+        let a = self.0.inner.lock().unwrap().take().unwrap();
+        let b = a.lines();
+        Box::new(Lines { buf: b })
+    }
+    fn for_each_line(&self, f: &mut dyn FnMut(&str) -> Result<ControlFlow<()>>) -> Result<()> {
+        for_each_line_from(&mut OsPipeInLock(self.0.lock()), f)
+    }
+    fn read_line_into(&self, buf: &mut String) -> Result<bool> {
+        read_line_from(&mut OsPipeInLock(self.0.lock()), buf)
+    }
+}
+
+impl std::convert::From<OwnedFd> for OsPipeIn {
+    #[inline(always)]
+    fn from(a: OwnedFd) -> Self {
+        Self::with(File::from(a))
+    }
+}
+impl std::convert::TryFrom<OsPipeIn> for OwnedFd {
+    type Error = std::io::Error;
+    /// This fails if the data is buffered in the `OsPipeIn`, or the reader
+    /// has been taken by `lines()`.
+    #[inline(always)]
+    fn try_from(a: OsPipeIn) -> Result<Self> {
+        Ok(OwnedFd::from(a.0.into_file()?))
+    }
+}
+impl std::convert::TryFrom<OsPipeIn> for Stdio {
+    type Error = std::io::Error;
+    /// This fails if the data is buffered in the `OsPipeIn`, or the reader
+    /// has been taken by `lines()`.
+    #[inline(always)]
+    fn try_from(a: OsPipeIn) -> Result<Self> {
+        Ok(Stdio::from(a.0.into_file()?))
+    }
+}
+
+/// A locked reference to `OsPipeIn`
+#[derive(Debug)]
+pub struct OsPipeInLock<'a>(LockableOsPipeInLock<'a>);
+impl Read for OsPipeInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+impl BufRead for OsPipeInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.0.fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.0.consume(amt)
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamOut
+/// The os pipe output stream.
+#[derive(Debug)]
+pub struct OsPipeOut(LockableOsPipeOut);
+impl OsPipeOut {
+    pub fn with(a: File) -> Self {
+        Self(LockableOsPipeOut::with(a))
+    }
+}
+impl StreamOut for OsPipeOut {
+    fn lock(&self) -> Box<dyn StreamOutLock + '_> {
+        Box::new(OsPipeOutLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn write_line(&self, string: String) -> Result<()> {
+        self.lock().write_fmt(format_args!("{}\n", string))
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        write_lines_to(&mut *self.lock(), lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        write_lines_to(&mut *self.lock(), [string])
+    }
+    fn flush_line(&self) -> Result<()> {
+        self.lock().flush()
+    }
+}
+
+impl std::convert::From<OwnedFd> for OsPipeOut {
+    #[inline(always)]
+    fn from(a: OwnedFd) -> Self {
+        Self::with(File::from(a))
+    }
+}
+impl std::convert::TryFrom<OsPipeOut> for OwnedFd {
+    type Error = std::io::Error;
+    /// The data buffered in the `OsPipeOut` is flushed, this fails if it fails.
+    #[inline(always)]
+    fn try_from(a: OsPipeOut) -> Result<Self> {
+        Ok(OwnedFd::from(a.0.into_file()?))
+    }
+}
+impl std::convert::TryFrom<OsPipeOut> for Stdio {
+    type Error = std::io::Error;
+    /// The data buffered in the `OsPipeOut` is flushed, this fails if it fails.
+    #[inline(always)]
+    fn try_from(a: OsPipeOut) -> Result<Self> {
+        Ok(Stdio::from(a.0.into_file()?))
+    }
+}
+
+/// A locked reference to `OsPipeOut`
+#[derive(Debug)]
+pub struct OsPipeOutLock<'a>(LockableOsPipeOutLock<'a>);
+impl StreamOutLock for OsPipeOutLock<'_> {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        self.0.buffer()
+    }
+}
+impl Write for OsPipeOutLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamErr
+/// The os pipe error stream.
+#[derive(Debug)]
+pub struct OsPipeErr(LockableOsPipeOut);
+impl OsPipeErr {
+    pub fn with(a: File) -> Self {
+        Self(LockableOsPipeOut::with(a))
+    }
+}
+impl StreamErr for OsPipeErr {
+    fn lock(&self) -> Box<dyn StreamErrLock + '_> {
+        Box::new(OsPipeErrLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn write_line(&self, string: String) -> Result<()> {
+        self.lock().write_fmt(format_args!("{}\n", string))
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        write_lines_to(&mut *self.lock(), lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        write_lines_to(&mut *self.lock(), [string])
+    }
+    fn flush_line(&self) -> Result<()> {
+        self.lock().flush()
+    }
+}
+
+impl std::convert::From<OsPipeOut> for OsPipeErr {
+    #[inline(always)]
+    fn from(a: OsPipeOut) -> Self {
+        Self(a.0)
+    }
+}
+
+impl std::convert::From<OwnedFd> for OsPipeErr {
+    #[inline(always)]
+    fn from(a: OwnedFd) -> Self {
+        Self::with(File::from(a))
+    }
+}
+impl std::convert::TryFrom<OsPipeErr> for OwnedFd {
+    type Error = std::io::Error;
+    /// The data buffered in the `OsPipeErr` is flushed, this fails if it fails.
+    #[inline(always)]
+    fn try_from(a: OsPipeErr) -> Result<Self> {
+        Ok(OwnedFd::from(a.0.into_file()?))
+    }
+}
+impl std::convert::TryFrom<OsPipeErr> for Stdio {
+    type Error = std::io::Error;
+    /// The data buffered in the `OsPipeErr` is flushed, this fails if it fails.
+    #[inline(always)]
+    fn try_from(a: OsPipeErr) -> Result<Self> {
+        Ok(Stdio::from(a.0.into_file()?))
+    }
+}
+
+/// A locked reference to `OsPipeErr`
+#[derive(Debug)]
+pub struct OsPipeErrLock<'a>(LockableOsPipeOutLock<'a>);
+impl StreamErrLock for OsPipeErrLock<'_> {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        self.0.buffer()
+    }
+}
+impl Write for OsPipeErrLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+const LINE_BUF_SIZE: usize = 8 * 1024;
+
+#[derive(Debug)]
+struct LockableOsPipeIn {
+    inner: Mutex<Option<BufReader<File>>>,
+}
+impl LockableOsPipeIn {
+    pub fn with(a: File) -> Self {
+        LockableOsPipeIn {
+            inner: Mutex::new(Some(BufReader::with_capacity(LINE_BUF_SIZE, a))),
+        }
+    }
+    fn into_file(self) -> Result<File> {
+        let a = self.inner.into_inner().unwrap_or_else(|e| e.into_inner());
+        let a = match a {
+            Some(a) => a,
+            None => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    "the reader has been taken by lines()",
+                ))
+            }
+        };
+        if !a.buffer().is_empty() {
+            // the buffered data would be lost
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the data is buffered in the reader",
+            ));
+        }
+        Ok(a.into_inner())
+    }
+    pub fn lock(&self) -> LockableOsPipeInLock<'_> {
+        LockableOsPipeInLock {
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LockableOsPipeInLock<'a> {
+    inner: MutexGuard<'a, Option<BufReader<File>>>,
+}
+impl Read for LockableOsPipeInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.as_mut().unwrap().read(buf)
+    }
+}
+impl BufRead for LockableOsPipeInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.as_mut().unwrap().fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.inner.as_mut().unwrap().consume(amt)
+    }
+}
+
+#[derive(Debug)]
+struct LockableOsPipeOut {
+    inner: Mutex<BufWriter<File>>,
+}
+impl LockableOsPipeOut {
+    fn with(a: File) -> Self {
+        LockableOsPipeOut {
+            inner: Mutex::new(BufWriter::with_capacity(LINE_BUF_SIZE, a)),
+        }
+    }
+    fn into_file(self) -> Result<File> {
+        let a = self.inner.into_inner().unwrap_or_else(|e| e.into_inner());
+        a.into_inner().map_err(|e| e.into_error())
+    }
+    pub fn lock(&self) -> LockableOsPipeOutLock<'_> {
+        LockableOsPipeOutLock {
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LockableOsPipeOutLock<'a> {
+    inner: MutexGuard<'a, BufWriter<File>>,
+}
+impl LockableOsPipeOutLock<'_> {
+    #[inline(always)]
+    pub fn buffer(&self) -> &[u8] {
+        self.inner.buffer()
+    }
+}
+impl Write for LockableOsPipeOutLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.inner.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub struct Lines {
+    buf: std::io::Lines<BufReader<File>>,
+}
+impl Iterator for Lines {
+    type Item = Result<String>;
+    fn next(&mut self) -> Option<Result<String>> {
+        self.buf.next()
+    }
+}
+impl NextLine for Lines {}
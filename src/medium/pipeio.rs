@@ -1,11 +1,54 @@
 //!
 //! The in-memory fifo stream, like linux pipe. You can use for communication between threads.
 //!
+//! The buffers filled by [`PipeOut`] are moved to [`PipeIn`] without copying,
+//! and the drained buffers come back to [`PipeOut`] to be reused.
+//! So steady-state piping does not allocate.
+//!
+//! The [`PipeOut`] and [`PipeErr`] can be cloned for many producers.
+//! Each clone has its own buffer, and the reader gets EOF after all clones
+//! are dropped or closed. A `write_line()` is never split across chunks, so the lines
+//! from different producers do not interleave.
+//!
+//! The reads and writes wait without limit by default. For bounded waits,
+//! there are [`PipeIn::lines_timeout()`], [`PipeInLock::try_fill_buf()`],
+//! [`PipeOut::try_write_line()`], [`PipeOut::write_timeout()`] and so on.
+//! They return `ErrorKind::WouldBlock` or `ErrorKind::TimedOut`.
+//!
+//! The [`PipeOut::close()`] flushes and signals EOF without dropping.
+//! A [`PipeOut`] that is dropped without `close()` flushes its buffer,
+//! and the error of it is given to [`PipeOut::on_drop_error()`]. The flush
+//! on drop waits for the reader while it is alive, the data is not lost.
+//! Writing to the pipe that the reader is gone or that is closed returns
+//! `ErrorKind::BrokenPipe`.
+//!
+//! The sizes can be tuned by [`PipeBuilder`], small chunks for interactive
+//! stages and large chunks for bulk stages. With [`PipeBuilder::max_latency()`],
+//! the buffered data is sent after the duration even if the writer is idle.
+//! With [`PipeBuilder::buffer_mode()`], the writer sends on each write or
+//! each newline, like libc stdio.
+//!
+//! The [`pipe()`] bounds the chunks in flight by the count. The [`pipe_bytes()`]
+//! bounds them by the total bytes, so the memory between stages is predictable.
+//!
+//! For more throughput, see [`ringpipeio`].
+//!
+//! [`ringpipeio`]: crate::medium::ringpipeio
+//!
+use super::budget::Budget;
+use super::latency::{self, FlushAged};
+use super::wait::{self, Sender, Wait};
 use crate::*;
 
 use std::io::{BufRead, BufReader, Read, Write};
+use std::ops::ControlFlow;
+use std::panic::{RefUnwindSafe, UnwindSafe};
 use std::sync::mpsc::{Receiver, SyncSender};
-use std::sync::{Mutex, MutexGuard};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// The callback that is called with the error of the final flush on drop.
+pub type DropErrorFn = dyn Fn(&std::io::Error) + Send + Sync + UnwindSafe + RefUnwindSafe;
 
 //----------------------------------------------------------------------
 /// create in-memory fifo stream and return ([`PipeOut`], [`PipeIn`]).
@@ -15,8 +58,142 @@ use std::sync::{Mutex, MutexGuard};
 ///
 #[inline(always)]
 pub fn pipe(sz: usize) -> (PipeOut, PipeIn) {
-    let (sender, receiver) = std::sync::mpsc::sync_channel(sz);
-    (PipeOut::with(sender), PipeIn::with(receiver))
+    PipeBuilder::new().capacity(sz).build_pipe()
+}
+
+/// create in-memory fifo stream that is bounded by the bytes in flight,
+/// and return ([`PipeOut`], [`PipeIn`]).
+///
+/// The writer waits while the chunks in flight would exceed `bytes`.
+/// The bytes of a chunk is the capacity of its buffer.
+///
+/// [`PipeOut`]: PipeOut
+/// [`PipeIn`]: PipeIn
+///
+#[inline(always)]
+pub fn pipe_bytes(bytes: usize) -> (PipeOut, PipeIn) {
+    PipeBuilder::new().capacity_bytes(bytes).build_pipe()
+}
+
+/// The builder of the in-memory fifo stream.
+///
+/// ```rust
+/// use runnel::medium::pipeio::PipeBuilder;
+///
+/// // an interactive stage, that sends each small write soon
+/// let (sout, sin) = PipeBuilder::new()
+///     .chunk_bytes(256)
+///     .read_buf(256)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipeBuilder {
+    capacity: usize,
+    chunk_bytes: usize,
+    read_buf: usize,
+    max_latency: Option<Duration>,
+    buffer_mode: BufferMode,
+    capacity_bytes: Option<usize>,
+}
+impl PipeBuilder {
+    /// create builder
+    pub fn new() -> Self {
+        Self {
+            capacity: 1,
+            chunk_bytes: BUF_SZ,
+            read_buf: LINE_BUF_SIZE,
+            max_latency: None,
+            buffer_mode: BufferMode::Full,
+            capacity_bytes: None,
+        }
+    }
+    /// set the number of the chunks in the channel. The default is 1.
+    pub fn capacity(mut self, sz: usize) -> Self {
+        self.capacity = sz;
+        self
+    }
+    /// set the total bytes of the chunks in the channel, instead of the count.
+    ///
+    /// The bytes of a chunk is the capacity of its buffer. A chunk that is
+    /// larger than `bytes` is sent when the channel is empty.
+    pub fn capacity_bytes(mut self, bytes: usize) -> Self {
+        self.capacity_bytes = Some(bytes);
+        self
+    }
+    /// set the bytes of a chunk. The default is 16KiB.
+    ///
+    /// The writer sends its buffer as a chunk, when it holds this or more.
+    pub fn chunk_bytes(mut self, sz: usize) -> Self {
+        self.chunk_bytes = sz.max(1);
+        self
+    }
+    /// set the buffer size of the reader. The default is 1KiB.
+    pub fn read_buf(mut self, sz: usize) -> Self {
+        self.read_buf = sz.max(1);
+        self
+    }
+    /// set the max latency. The default is none.
+    ///
+    /// The data buffered longer than `dur` is sent by a background thread,
+    /// even if the chunk is not full. The thread is shared by all the
+    /// writers, it does not wait for a full pipe, and retries later.
+    pub fn max_latency(mut self, dur: Duration) -> Self {
+        self.max_latency = Some(dur);
+        self
+    }
+    /// set the buffering mode of the writer. The default is `BufferMode::Full`.
+    ///
+    /// With `BufferMode::Line`, the writer sends its buffer when a newline
+    /// is written. With `BufferMode::Unbuffered`, on each write.
+    pub fn buffer_mode(mut self, mode: BufferMode) -> Self {
+        self.buffer_mode = mode;
+        self
+    }
+    /// build to ([`PipeOut`], [`PipeIn`])
+    ///
+    /// With the max latency, it fails if the background thread can not be
+    /// spawned.
+    pub fn build(self) -> Result<(PipeOut, PipeIn)> {
+        if self.max_latency.is_some() {
+            latency::start()?;
+        }
+        Ok(self.build_pipe())
+    }
+    /// build without the max latency check, it does not fail.
+    fn build_pipe(self) -> (PipeOut, PipeIn) {
+        let (sender, receiver, budget, pool_sz) = match self.capacity_bytes {
+            Some(bytes) => {
+                let (sender, receiver) = std::sync::mpsc::channel();
+                let budget = Arc::new(Budget::new(bytes));
+                // the buffers in the budget, and one on each side
+                let pool_sz = (bytes / self.chunk_bytes).min(1024) + 2;
+                (Sender::Unbounded(sender), receiver, Some(budget), pool_sz)
+            }
+            None => {
+                let (sender, receiver) = std::sync::mpsc::sync_channel(self.capacity);
+                // the buffers in the channel, and one on each side
+                (Sender::Bounded(sender), receiver, None, self.capacity + 2)
+            }
+        };
+        let (r_sender, r_receiver) = std::sync::mpsc::sync_channel(pool_sz);
+        let mut raw_out = RawPipeOut::with_recycle(sender, r_receiver);
+        raw_out.chunk_sz = self.chunk_bytes;
+        raw_out.latency = self.max_latency;
+        raw_out.mode = self.buffer_mode;
+        raw_out.budget = budget.clone();
+        let mut raw_in = RawPipeIn::with_recycle(receiver, r_sender);
+        raw_in.budget = budget;
+        (
+            PipeOut(LockablePipeOut::with(raw_out)),
+            PipeIn(LockablePipeIn::with_raw(raw_in, self.read_buf)),
+        )
+    }
+}
+impl Default for PipeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 //----------------------------------------------------------------------
@@ -28,6 +205,21 @@ impl PipeIn {
     pub fn with(a: Receiver<Vec<u8>>) -> Self {
         Self(LockablePipeIn::with(a))
     }
+    /// Returns a locked reference, that has the non-blocking reads.
+    pub fn lock(&self) -> PipeInLock<'_> {
+        PipeInLock(self.0.lock())
+    }
+    /// Returns an iterator over the lines, each line waits at most `dur`.
+    ///
+    /// On timeout, the iterator returns `ErrorKind::TimedOut`, and it can be
+    /// continued. The partial line read so far is kept.
+    pub fn lines_timeout(&self, dur: Duration) -> LinesTimeout<'_> {
+        LinesTimeout {
+            buf: self.0.lock(),
+            pending: Vec::new(),
+            dur,
+        }
+    }
 }
 impl StreamIn for PipeIn {
     fn lock_bufread(&self) -> Box<dyn BufRead + '_> {
@@ -41,11 +233,27 @@ impl StreamIn for PipeIn {
         let b = a.lines();
         Box::new(Lines { buf: b })
     }
+    fn for_each_line(&self, f: &mut dyn FnMut(&str) -> Result<ControlFlow<()>>) -> Result<()> {
+        for_each_line_from(&mut PipeInLock(self.0.lock()), f)
+    }
+    fn read_line_into(&self, buf: &mut String) -> Result<bool> {
+        read_line_from(&mut PipeInLock(self.0.lock()), buf)
+    }
 }
 
 /// A locked reference to `PipeIn`
 #[derive(Debug)]
 pub struct PipeInLock<'a>(LockablePipeInLock<'a>);
+impl PipeInLock<'_> {
+    /// like `fill_buf()`, but if no data is ready, returns `ErrorKind::WouldBlock`.
+    pub fn try_fill_buf(&mut self) -> Result<&[u8]> {
+        self.0.fill_buf_with(Wait::Try)
+    }
+    /// like `fill_buf()`, but waits at most `dur`, or returns `ErrorKind::TimedOut`.
+    pub fn fill_buf_timeout(&mut self, dur: Duration) -> Result<&[u8]> {
+        self.0.fill_buf_with(Wait::timeout(dur))
+    }
+}
 impl Read for PipeInLock<'_> {
     #[inline(always)]
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
@@ -74,6 +282,56 @@ impl PipeOut {
         Self(LockablePipeOut::with(RawPipeOut::with(sender)))
     }
 }
+impl Clone for PipeOut {
+    /// The clone has its own empty buffer, and shares the channel.
+    fn clone(&self) -> Self {
+        Self(self.0.clone_empty())
+    }
+}
+impl PipeOut {
+    /// set `f` that is called when the final flush on drop fails.
+    ///
+    /// The buffered data is flushed when the last reference is dropped.
+    /// The error of it can not be returned, so `f` is called with it.
+    /// The clones made after this share `f`.
+    pub fn on_drop_error<F>(self, f: F) -> Self
+    where
+        F: Fn(&std::io::Error) + Send + Sync + UnwindSafe + RefUnwindSafe + 'static,
+    {
+        self.0.lock().inner.on_drop_error = Some(Arc::new(f));
+        self
+    }
+    /// like `write_line()`, but if it has to wait, returns `ErrorKind::WouldBlock`.
+    ///
+    /// The `string` is written only when this returns `Ok`.
+    pub fn try_write_line(&self, string: String) -> Result<()> {
+        self.0.lock().inner.write_line_with(&string, Wait::Try)
+    }
+    /// like `write_line()`, but waits at most `dur`, or returns `ErrorKind::TimedOut`.
+    ///
+    /// The `string` is written only when this returns `Ok`.
+    pub fn write_timeout(&self, string: String, dur: Duration) -> Result<()> {
+        self.0
+            .lock()
+            .inner
+            .write_line_with(&string, Wait::timeout(dur))
+    }
+    /// like `flush_line()`, but if it has to wait, returns `ErrorKind::WouldBlock`.
+    pub fn try_flush_line(&self) -> Result<()> {
+        self.0.lock().inner.flush_with(Wait::Try)
+    }
+    /// like `flush_line()`, but waits at most `dur`, or returns `ErrorKind::TimedOut`.
+    pub fn flush_timeout(&self, dur: Duration) -> Result<()> {
+        self.0.lock().inner.flush_with(Wait::timeout(dur))
+    }
+    /// flush the buffer and signal EOF, without dropping this.
+    ///
+    /// The later writes return `ErrorKind::BrokenPipe`. With the clones,
+    /// the reader gets EOF after all of them are closed or dropped.
+    pub fn close(&self) -> Result<()> {
+        self.0.lock().inner.close()
+    }
+}
 impl StreamOut for PipeOut {
     #[inline(always)]
     fn lock(&self) -> Box<dyn StreamOutLock + '_> {
@@ -83,7 +341,13 @@ impl StreamOut for PipeOut {
         false
     }
     fn write_line(&self, string: String) -> Result<()> {
-        self.lock().write_fmt(format_args!("{}\n", string))
+        self.0.lock().inner.write_line(&string)
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        self.0.lock().inner.write_lines(lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        self.0.lock().inner.write_line(string)
     }
     fn flush_line(&self) -> Result<()> {
         self.lock().flush()
@@ -121,6 +385,56 @@ impl PipeErr {
         Self(LockablePipeOut::with(RawPipeOut::with(sender)))
     }
 }
+impl Clone for PipeErr {
+    /// The clone has its own empty buffer, and shares the channel.
+    fn clone(&self) -> Self {
+        Self(self.0.clone_empty())
+    }
+}
+impl PipeErr {
+    /// set `f` that is called when the final flush on drop fails.
+    ///
+    /// The buffered data is flushed when the last reference is dropped.
+    /// The error of it can not be returned, so `f` is called with it.
+    /// The clones made after this share `f`.
+    pub fn on_drop_error<F>(self, f: F) -> Self
+    where
+        F: Fn(&std::io::Error) + Send + Sync + UnwindSafe + RefUnwindSafe + 'static,
+    {
+        self.0.lock().inner.on_drop_error = Some(Arc::new(f));
+        self
+    }
+    /// like `write_line()`, but if it has to wait, returns `ErrorKind::WouldBlock`.
+    ///
+    /// The `string` is written only when this returns `Ok`.
+    pub fn try_write_line(&self, string: String) -> Result<()> {
+        self.0.lock().inner.write_line_with(&string, Wait::Try)
+    }
+    /// like `write_line()`, but waits at most `dur`, or returns `ErrorKind::TimedOut`.
+    ///
+    /// The `string` is written only when this returns `Ok`.
+    pub fn write_timeout(&self, string: String, dur: Duration) -> Result<()> {
+        self.0
+            .lock()
+            .inner
+            .write_line_with(&string, Wait::timeout(dur))
+    }
+    /// like `flush_line()`, but if it has to wait, returns `ErrorKind::WouldBlock`.
+    pub fn try_flush_line(&self) -> Result<()> {
+        self.0.lock().inner.flush_with(Wait::Try)
+    }
+    /// like `flush_line()`, but waits at most `dur`, or returns `ErrorKind::TimedOut`.
+    pub fn flush_timeout(&self, dur: Duration) -> Result<()> {
+        self.0.lock().inner.flush_with(Wait::timeout(dur))
+    }
+    /// flush the buffer and signal EOF, without dropping this.
+    ///
+    /// The later writes return `ErrorKind::BrokenPipe`. With the clones,
+    /// the reader gets EOF after all of them are closed or dropped.
+    pub fn close(&self) -> Result<()> {
+        self.0.lock().inner.close()
+    }
+}
 impl StreamErr for PipeErr {
     #[inline(always)]
     fn lock(&self) -> Box<dyn StreamErrLock + '_> {
@@ -130,7 +444,13 @@ impl StreamErr for PipeErr {
         false
     }
     fn write_line(&self, string: String) -> Result<()> {
-        self.lock().write_fmt(format_args!("{}\n", string))
+        self.0.lock().inner.write_line(&string)
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        self.0.lock().inner.write_lines(lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        self.0.lock().inner.write_line(string)
     }
     fn flush_line(&self) -> Result<()> {
         self.lock().flush()
@@ -174,11 +494,11 @@ struct LockablePipeIn {
 }
 impl LockablePipeIn {
     pub fn with(a: Receiver<Vec<u8>>) -> Self {
+        Self::with_raw(RawPipeIn::new(a), LINE_BUF_SIZE)
+    }
+    fn with_raw(a: RawPipeIn, read_buf: usize) -> Self {
         LockablePipeIn {
-            inner: Mutex::new(Some(BufReader::with_capacity(
-                LINE_BUF_SIZE,
-                RawPipeIn::new(a),
-            ))),
+            inner: Mutex::new(Some(BufReader::with_capacity(read_buf, a))),
         }
     }
     pub fn lock(&self) -> LockablePipeInLock<'_> {
@@ -192,6 +512,17 @@ impl LockablePipeIn {
 struct LockablePipeInLock<'a> {
     inner: MutexGuard<'a, Option<BufReader<RawPipeIn>>>,
 }
+impl LockablePipeInLock<'_> {
+    fn fill_buf_with(&mut self, wait: Wait) -> Result<&[u8]> {
+        let a = self.inner.as_mut().unwrap();
+        a.get_mut().wait = wait;
+        let r = a.fill_buf().map(|b| b.len());
+        a.get_mut().wait = Wait::Block;
+        r?;
+        // this returns the buffered data without reading
+        a.fill_buf()
+    }
+}
 impl Read for LockablePipeInLock<'_> {
     #[inline(always)]
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
@@ -211,13 +542,20 @@ impl BufRead for LockablePipeInLock<'_> {
 
 #[derive(Debug)]
 struct LockablePipeOut {
-    inner: Mutex<RawPipeOut>,
+    inner: Arc<Mutex<RawPipeOut>>,
 }
 impl LockablePipeOut {
     fn with(a: RawPipeOut) -> Self {
-        LockablePipeOut {
-            inner: Mutex::new(a),
+        let latency = a.latency;
+        let inner = Arc::new(Mutex::new(a));
+        if let Some(dur) = latency {
+            // the timer thread is started by the builder
+            latency::register(Arc::downgrade(&inner), dur);
         }
+        LockablePipeOut { inner }
+    }
+    fn clone_empty(&self) -> Self {
+        Self::with(self.lock().inner.clone_empty())
     }
     pub fn lock(&self) -> LockablePipeOutLock<'_> {
         LockablePipeOutLock {
@@ -225,7 +563,12 @@ impl LockablePipeOut {
         }
     }
 }
-
+impl Drop for LockablePipeOut {
+    fn drop(&mut self) {
+        // flush here, the flusher thread may hold the last `Arc` a moment
+        self.lock().inner.flush_on_drop();
+    }
+}
 #[derive(Debug)]
 struct LockablePipeOutLock<'a> {
     inner: MutexGuard<'a, RawPipeOut>,
@@ -258,12 +601,70 @@ impl Iterator for Lines {
 }
 impl NextLine for Lines {}
 
+/// An iterator over the lines with a timeout. See [`PipeIn::lines_timeout()`].
+pub struct LinesTimeout<'a> {
+    buf: LockablePipeInLock<'a>,
+    pending: Vec<u8>,
+    dur: Duration,
+}
+impl Iterator for LinesTimeout<'_> {
+    type Item = Result<String>;
+    fn next(&mut self) -> Option<Result<String>> {
+        let wait = Wait::timeout(self.dur);
+        loop {
+            let (amt, found) = {
+                let src = match self.buf.fill_buf_with(wait) {
+                    Ok(a) => a,
+                    Err(err) => return Some(Err(err)),
+                };
+                if src.is_empty() {
+                    // EOF
+                    if self.pending.is_empty() {
+                        return None;
+                    }
+                    break;
+                }
+                match src.iter().position(|&b| b == b'\n') {
+                    Some(idx) => {
+                        self.pending.extend_from_slice(&src[..=idx]);
+                        (idx + 1, true)
+                    }
+                    None => {
+                        self.pending.extend_from_slice(src);
+                        (src.len(), false)
+                    }
+                }
+            };
+            self.buf.consume(amt);
+            if found {
+                break;
+            }
+        }
+        let mut v = std::mem::take(&mut self.pending);
+        if v.last() == Some(&b'\n') {
+            v.pop();
+            if v.last() == Some(&b'\r') {
+                v.pop();
+            }
+        }
+        Some(String::from_utf8(v).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )
+        }))
+    }
+}
+
 #[derive(Debug)]
 struct RawPipeIn {
     buf: Vec<u8>,
     pos: usize,
     amt: usize,
     reciever: Receiver<Vec<u8>>,
+    recycle: Option<SyncSender<Vec<u8>>>,
+    wait: Wait,
+    budget: Option<Arc<Budget>>,
 }
 impl RawPipeIn {
     fn new(a: Receiver<Vec<u8>>) -> Self {
@@ -272,15 +673,50 @@ impl RawPipeIn {
             pos: 0,
             amt: 0,
             reciever: a,
+            recycle: None,
+            wait: Wait::Block,
+            budget: None,
+        }
+    }
+    fn with_recycle(a: Receiver<Vec<u8>>, recycle: SyncSender<Vec<u8>>) -> Self {
+        let mut r = Self::new(a);
+        r.recycle = Some(recycle);
+        r
+    }
+    /// receive a chunk. `Ok(None)` means EOF.
+    fn recv(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+        let a = self.wait.recv(&self.reciever)?;
+        if let (Some(a), Some(budget)) = (a.as_ref(), self.budget.as_ref()) {
+            budget.release(a.capacity());
+        }
+        Ok(a)
+    }
+    /// give back the drained buffer to the sender.
+    fn drained(&mut self) {
+        let mut a = std::mem::take(&mut self.buf);
+        self.pos = 0;
+        self.amt = 0;
+        if let Some(recycle) = self.recycle.as_ref() {
+            a.clear();
+            // the pool is full or the sender is gone, then just drop it
+            let _ = recycle.try_send(a);
+        }
+    }
+}
+impl Drop for RawPipeIn {
+    fn drop(&mut self) {
+        if let Some(budget) = self.budget.as_ref() {
+            // wake up the writers waiting for the budget
+            budget.close();
         }
     }
 }
 impl Read for RawPipeIn {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         if self.buf.is_empty() {
-            self.buf = match self.reciever.recv() {
-                Ok(s) => s,
-                Err(_) => return Ok(0),
+            self.buf = match self.recv()? {
+                Some(s) => s,
+                None => return Ok(0),
             };
         }
         //
@@ -302,9 +738,7 @@ impl Read for RawPipeIn {
         };
         //
         if self.pos >= self.buf.as_slice().len() {
-            self.buf.clear();
-            self.pos = 0;
-            self.amt = 0;
+            self.drained();
         }
         //
         Ok(len)
@@ -318,7 +752,10 @@ impl BufRead for RawPipeIn {
             self.amt = 0;
         }
         if self.buf.is_empty() {
-            self.buf = self.reciever.recv().unwrap();
+            self.buf = match self.recv()? {
+                Some(s) => s,
+                None => return Ok(&[]),
+            };
         }
         //
         let src = {
@@ -345,42 +782,220 @@ impl BufRead for RawPipeIn {
     }
 }
 
-#[derive(Debug)]
+pub(crate) const BUF_SZ: usize = 4 * 4 * 1024;
+
 struct RawPipeOut {
     buf: Vec<u8>,
-    sender: SyncSender<Vec<u8>>,
+    // `None` after closed
+    sender: Option<Sender<Vec<u8>>>,
+    // shared by the clones
+    recycle: Option<Arc<Mutex<Receiver<Vec<u8>>>>>,
+    on_drop_error: Option<Arc<DropErrorFn>>,
+    chunk_sz: usize,
+    mode: BufferMode,
+    latency: Option<Duration>,
+    // the time when the buffer became non-empty, with the latency
+    since: Option<Instant>,
+    // shared with the reader, for the byte-bounded pipe
+    budget: Option<Arc<Budget>>,
 }
 impl RawPipeOut {
     pub fn with(a: SyncSender<Vec<u8>>) -> Self {
+        Self::with_sender(Sender::Bounded(a))
+    }
+    fn with_sender(a: Sender<Vec<u8>>) -> Self {
         Self {
             buf: Vec::new(),
-            sender: a,
+            sender: Some(a),
+            recycle: None,
+            on_drop_error: None,
+            chunk_sz: BUF_SZ,
+            mode: BufferMode::Full,
+            latency: None,
+            since: None,
+            budget: None,
+        }
+    }
+    fn with_recycle(a: Sender<Vec<u8>>, recycle: Receiver<Vec<u8>>) -> Self {
+        let mut r = Self::with_sender(a);
+        r.recycle = Some(Arc::new(Mutex::new(recycle)));
+        r
+    }
+    fn clone_empty(&self) -> Self {
+        Self {
+            buf: Vec::new(),
+            sender: self.sender.clone(),
+            recycle: self.recycle.clone(),
+            on_drop_error: self.on_drop_error.clone(),
+            chunk_sz: self.chunk_sz,
+            mode: self.mode,
+            latency: self.latency,
+            since: None,
+            budget: self.budget.clone(),
+        }
+    }
+    /// take a empty buffer from the pool, or allocate new one.
+    fn take_buf(&mut self) -> Vec<u8> {
+        // an other clone is taking, then do not wait for it
+        let a = self.recycle.as_ref().and_then(|a| match a.try_lock() {
+            Ok(b) => b.try_recv().ok(),
+            Err(_) => None,
+        });
+        match a {
+            Some(a) => a,
+            None => Vec::with_capacity(self.chunk_sz),
+        }
+    }
+    /// write `string` and a newline, they are sent in the same chunk.
+    #[inline(always)]
+    fn write_line(&mut self, string: &str) -> Result<()> {
+        self.write_line_with(string, Wait::Block)
+    }
+    /// write the lines, each line is sent in the same chunk with its newline.
+    fn write_lines(&mut self, lines: Vec<String>) -> Result<()> {
+        for string in lines {
+            self.write_line_with(&string, Wait::Block)?;
+        }
+        Ok(())
+    }
+    fn write_line_with(&mut self, string: &str, wait: Wait) -> Result<()> {
+        if self.sender.is_none() {
+            return Err(wait::closed());
+        }
+        if self.is_full(false) {
+            self.flush_with(wait)?;
+        }
+        self.buf.extend_from_slice(string.as_bytes());
+        self.buf.push(b'\n');
+        self.touch();
+        self.flush_full(wait, true)
+    }
+    /// note the time of the oldest data, for the max latency.
+    #[inline(always)]
+    fn touch(&mut self) {
+        if self.latency.is_some() && self.since.is_none() && !self.buf.is_empty() {
+            self.since = Some(Instant::now());
+        }
+    }
+    /// Returns true if the buffer should be sent, by the buffering mode.
+    #[inline(always)]
+    fn is_full(&self, newline: bool) -> bool {
+        match self.mode {
+            BufferMode::Unbuffered => !self.buf.is_empty(),
+            BufferMode::Line => newline || self.buf.len() >= self.chunk_sz,
+            BufferMode::Full => self.buf.len() >= self.chunk_sz,
+        }
+    }
+    /// send the full chunk. If the pipe is not ready, the data is kept and
+    /// the error is returned by the next call. The other errors, like the
+    /// reader is gone, are returned at once.
+    fn flush_full(&mut self, wait: Wait, newline: bool) -> Result<()> {
+        if self.is_full(newline) {
+            match self.flush_with(wait) {
+                Err(err) if !wait::is_not_ready(&err) => return Err(err),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+    fn flush_with(&mut self, wait: Wait) -> Result<()> {
+        if self.buf.is_empty() {
+            // an empty buffer would be taken as EOF by the reader
+            return Ok(());
+        }
+        if self.sender.is_none() {
+            return Err(wait::closed());
         }
+        let bytes = self.buf.capacity();
+        if let Some(budget) = self.budget.as_ref() {
+            budget.acquire(bytes, wait)?;
+        }
+        // move the filled buffer to the reader, without copying
+        let a = self.take_buf();
+        let a = std::mem::replace(&mut self.buf, a);
+        if let Err((a, err)) = wait.send(self.sender.as_ref().unwrap(), a) {
+            if let Some(budget) = self.budget.as_ref() {
+                budget.release(bytes);
+            }
+            // keep the data as it was
+            self.buf = a;
+            return Err(err);
+        }
+        self.since = None;
+        Ok(())
+    }
+    /// flush and drop the sender. The reader gets EOF after all clones are closed.
+    fn close(&mut self) -> Result<()> {
+        let r = if self.sender.is_some() {
+            self.flush_with(Wait::Block)
+        } else {
+            Ok(())
+        };
+        self.sender = None;
+        r
     }
     #[inline(always)]
     pub fn buffer(&self) -> &[u8] {
         self.buf.as_slice()
     }
 }
+impl FlushAged for RawPipeOut {
+    #[inline(always)]
+    fn since(&self) -> Option<Instant> {
+        self.since
+    }
+    fn flush_aged(&mut self) {
+        let _ = self.flush_with(Wait::Try);
+    }
+}
+impl RawPipeOut {
+    /// the final flush on drop, and drop the sender.
+    fn flush_on_drop(&mut self) {
+        if self.sender.is_none() || self.buf.is_empty() {
+            self.sender = None;
+            return;
+        }
+        // wait for the reader, the send fails after the reader is gone
+        if let Err(err) = self.flush_with(Wait::Block) {
+            if let Some(f) = self.on_drop_error.as_ref() {
+                f(&err);
+            }
+        }
+        self.sender = None;
+    }
+}
+impl std::fmt::Debug for RawPipeOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RawPipeOut")
+            .field("buf", &self.buf)
+            .field("sender", &self.sender)
+            .field("recycle", &self.recycle)
+            .field("on_drop_error", &self.on_drop_error.is_some())
+            .field("chunk_sz", &self.chunk_sz)
+            .field("mode", &self.mode)
+            .field("latency", &self.latency)
+            .field("since", &self.since)
+            .finish()
+    }
+}
 impl Write for RawPipeOut {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         let src_len = buf.len();
+        if self.sender.is_none() {
+            return Err(wait::closed());
+        }
         // auto flush
-        //const BUF_SZ:usize = 1 * 1024;
-        const BUF_SZ: usize = 4 * 4 * 1024;
-        //const BUF_SZ:usize = 8 * 4 * 1024;
-        if self.buf.len() >= BUF_SZ {
+        if self.is_full(false) {
             self.flush()?;
         }
         self.buf.extend_from_slice(buf);
+        self.touch();
+        let newline = self.mode == BufferMode::Line && buf.contains(&b'\n');
+        self.flush_full(Wait::Block, newline)?;
         Ok(src_len)
     }
+    #[inline(always)]
     fn flush(&mut self) -> std::io::Result<()> {
-        let r = self.sender.send(self.buf.clone());
-        if let Err(err) = r {
-            return Err(std::io::Error::new(std::io::ErrorKind::Other, err));
-        }
-        self.buf.clear();
-        Ok(())
+        self.flush_with(Wait::Block)
     }
 }
@@ -0,0 +1,464 @@
+//!
+//! The in-memory ring buffer stream. You can use for communication between threads.
+//!
+//! This is backed by a single-producer, single-consumer ring buffer sized
+//! in bytes. Unlike [`pipeio`], a write is copied into the ring directly and
+//! a read borrows the ring directly, so no channel operation and no
+//! allocation are done per chunk. A side that has to wait spins for a while,
+//! and then parks its thread.
+//!
+//! The written bytes are visible to the reader at once, like an os pipe,
+//! so `flush()` has nothing to do.
+//!
+//! [`pipeio`]: crate::medium::pipeio
+//!
+//! # Examples
+//!
+//! ```rust
+//! use runnel::medium::ringpipeio::ring_pipe;
+//! use runnel::*;
+//!
+//! let (sout, sin) = ring_pipe(64 * 1024);
+//! let handler = std::thread::spawn(move || {
+//!     sout.write_line("ABCDE".to_string()).unwrap();
+//!     sout.write_line("efgh".to_string()).unwrap();
+//! });
+//! let mut lines_iter = sin.lines().map(|l| l.unwrap());
+//! assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+//! assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+//! assert_eq!(lines_iter.next(), None);
+//! assert!(handler.join().is_ok());
+//! ```
+//!
+use crate::*;
+
+use std::io::{BufRead, Read, Write};
+use std::sync::atomic::{fence, AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::Thread;
+
+//----------------------------------------------------------------------
+/// create in-memory ring buffer stream of `bytes` and return ([`RingPipeOut`], [`RingPipeIn`]).
+///
+/// The `bytes` is rounded up to 1 at least.
+///
+/// [`RingPipeOut`]: RingPipeOut
+/// [`RingPipeIn`]: RingPipeIn
+///
+pub fn ring_pipe(bytes: usize) -> (RingPipeOut, RingPipeIn) {
+    let ring = Arc::new(Ring::new(bytes.max(1)));
+    (
+        RingPipeOut(LockableRingPipeOut::with(RawRingPipeOut::new(ring.clone()))),
+        RingPipeIn(LockableRingPipeIn::with(RawRingPipeIn::new(ring))),
+    )
+}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamIn
+/// The in-memory ring buffer input stream.
+#[derive(Debug)]
+pub struct RingPipeIn(LockableRingPipeIn);
+impl StreamIn for RingPipeIn {
+    fn lock_bufread(&self) -> Box<dyn BufRead + '_> {
+        Box::new(RingPipeInLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn lines(&self) -> Box<dyn NextLine + '_> {
+        let a = self.0.inner.lock().unwrap().take().unwrap();
+        let b = a.lines();
+        Box::new(Lines { buf: b })
+    }
+}
+
+/// A locked reference to `RingPipeIn`
+#[derive(Debug)]
+pub struct RingPipeInLock<'a>(LockableRingPipeInLock<'a>);
+impl Read for RingPipeInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+impl BufRead for RingPipeInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.0.fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.0.consume(amt)
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamOut
+/// The in-memory ring buffer output stream.
+#[derive(Debug)]
+pub struct RingPipeOut(LockableRingPipeOut);
+impl StreamOut for RingPipeOut {
+    #[inline(always)]
+    fn lock(&self) -> Box<dyn StreamOutLock + '_> {
+        Box::new(RingPipeOutLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn write_line(&self, string: String) -> Result<()> {
+        self.write_str_line(&string)
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        write_lines_to(&mut self.0.lock(), lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        write_lines_to(&mut self.0.lock(), [string])
+    }
+    fn flush_line(&self) -> Result<()> {
+        self.lock().flush()
+    }
+}
+
+/// A locked reference to `RingPipeOut`
+#[derive(Debug)]
+pub struct RingPipeOutLock<'a>(LockableRingPipeOutLock<'a>);
+impl StreamOutLock for RingPipeOutLock<'_> {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        b""
+    }
+}
+impl Write for RingPipeOutLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamErr
+/// The in-memory ring buffer error stream.
+#[derive(Debug)]
+pub struct RingPipeErr(LockableRingPipeOut);
+impl StreamErr for RingPipeErr {
+    #[inline(always)]
+    fn lock(&self) -> Box<dyn StreamErrLock + '_> {
+        Box::new(RingPipeErrLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn write_line(&self, string: String) -> Result<()> {
+        self.write_str_line(&string)
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        write_lines_to(&mut self.0.lock(), lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        write_lines_to(&mut self.0.lock(), [string])
+    }
+    fn flush_line(&self) -> Result<()> {
+        self.lock().flush()
+    }
+}
+
+impl std::convert::From<RingPipeOut> for RingPipeErr {
+    #[inline(always)]
+    fn from(a: RingPipeOut) -> Self {
+        Self(a.0)
+    }
+}
+
+/// A locked reference to `RingPipeErr`
+#[derive(Debug)]
+pub struct RingPipeErrLock<'a>(LockableRingPipeOutLock<'a>);
+impl StreamErrLock for RingPipeErrLock<'_> {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        b""
+    }
+}
+impl Write for RingPipeErrLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+#[derive(Debug)]
+struct LockableRingPipeIn {
+    inner: Mutex<Option<RawRingPipeIn>>,
+}
+impl LockableRingPipeIn {
+    fn with(a: RawRingPipeIn) -> Self {
+        LockableRingPipeIn {
+            inner: Mutex::new(Some(a)),
+        }
+    }
+    pub fn lock(&self) -> LockableRingPipeInLock<'_> {
+        LockableRingPipeInLock {
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LockableRingPipeInLock<'a> {
+    inner: MutexGuard<'a, Option<RawRingPipeIn>>,
+}
+impl Read for LockableRingPipeInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.as_mut().unwrap().read(buf)
+    }
+}
+impl BufRead for LockableRingPipeInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.as_mut().unwrap().fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.inner.as_mut().unwrap().consume(amt)
+    }
+}
+
+#[derive(Debug)]
+struct LockableRingPipeOut {
+    inner: Mutex<RawRingPipeOut>,
+}
+impl LockableRingPipeOut {
+    fn with(a: RawRingPipeOut) -> Self {
+        LockableRingPipeOut {
+            inner: Mutex::new(a),
+        }
+    }
+    pub fn lock(&self) -> LockableRingPipeOutLock<'_> {
+        LockableRingPipeOutLock {
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LockableRingPipeOutLock<'a> {
+    inner: MutexGuard<'a, RawRingPipeOut>,
+}
+impl Write for LockableRingPipeOutLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.inner.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub struct Lines {
+    buf: std::io::Lines<RawRingPipeIn>,
+}
+impl Iterator for Lines {
+    type Item = Result<String>;
+    fn next(&mut self) -> Option<Result<String>> {
+        self.buf.next()
+    }
+}
+impl NextLine for Lines {}
+
+//----------------------------------------------------------------------
+#[derive(Debug)]
+struct RawRingPipeIn {
+    ring: Arc<Ring>,
+}
+impl RawRingPipeIn {
+    fn new(ring: Arc<Ring>) -> Self {
+        Self { ring }
+    }
+}
+impl Drop for RawRingPipeIn {
+    fn drop(&mut self) {
+        self.ring.r_closed.store(true, Ordering::Release);
+        self.ring.writer.wake();
+    }
+}
+impl Read for RawRingPipeIn {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = {
+            let src = self.fill_buf()?;
+            let len = src.len().min(buf.len());
+            buf[..len].copy_from_slice(&src[..len]);
+            len
+        };
+        self.consume(len);
+        Ok(len)
+    }
+}
+impl BufRead for RawRingPipeIn {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        let ring = self.ring.as_ref();
+        let head = ring.head.load(Ordering::Relaxed);
+        let ready =
+            || ring.tail.load(Ordering::Acquire) != head || ring.w_closed.load(Ordering::Acquire);
+        ring.reader.wait(ready);
+        // the writer may write the rest before it is closed
+        let tail = ring.tail.load(Ordering::Acquire);
+        let len = tail.wrapping_sub(head);
+        let idx = head % ring.cap;
+        let len = len.min(ring.cap - idx);
+        // the range is written by the writer, and it is not touched until consume()
+        Ok(unsafe { std::slice::from_raw_parts(ring.buf.add(idx), len) })
+    }
+    fn consume(&mut self, amt: usize) {
+        let ring = self.ring.as_ref();
+        let head = ring.head.load(Ordering::Relaxed);
+        // the head never passes the tail, or the writer would be overwritten
+        let avail = ring.tail.load(Ordering::Acquire).wrapping_sub(head);
+        ring.head
+            .store(head.wrapping_add(amt.min(avail)), Ordering::Release);
+        ring.writer.wake();
+    }
+}
+
+#[derive(Debug)]
+struct RawRingPipeOut {
+    ring: Arc<Ring>,
+}
+impl RawRingPipeOut {
+    fn new(ring: Arc<Ring>) -> Self {
+        Self { ring }
+    }
+}
+impl Drop for RawRingPipeOut {
+    fn drop(&mut self) {
+        self.ring.w_closed.store(true, Ordering::Release);
+        self.ring.reader.wake();
+    }
+}
+impl Write for RawRingPipeOut {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let ring = self.ring.as_ref();
+        let tail = ring.tail.load(Ordering::Relaxed);
+        let ready = || {
+            tail.wrapping_sub(ring.head.load(Ordering::Acquire)) < ring.cap
+                || ring.r_closed.load(Ordering::Acquire)
+        };
+        ring.writer.wait(ready);
+        if ring.r_closed.load(Ordering::Acquire) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "the reader is closed",
+            ));
+        }
+        let space = ring.cap - tail.wrapping_sub(ring.head.load(Ordering::Acquire));
+        let idx = tail % ring.cap;
+        let len = buf.len().min(space).min(ring.cap - idx);
+        // the range is consumed by the reader, and it is not read until the tail moves
+        unsafe {
+            std::ptr::copy_nonoverlapping(buf.as_ptr(), ring.buf.add(idx), len);
+        }
+        ring.tail.store(tail.wrapping_add(len), Ordering::Release);
+        ring.reader.wake();
+        Ok(len)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+//----------------------------------------------------------------------
+const SPIN_COUNT: usize = 100;
+
+/// The ring buffer shared by the both sides.
+///
+/// `head` and `tail` are the total bytes read and written, and they wrap.
+/// Only the reader moves `head`, and only the writer moves `tail`.
+#[derive(Debug)]
+struct Ring {
+    buf: *mut u8,
+    cap: usize,
+    head: AtomicUsize,
+    tail: AtomicUsize,
+    r_closed: AtomicBool,
+    w_closed: AtomicBool,
+    reader: Waiter,
+    writer: Waiter,
+}
+// the ranges of `buf` are owned by the reader or the writer, by `head` and `tail`.
+unsafe impl Send for Ring {}
+unsafe impl Sync for Ring {}
+impl Ring {
+    fn new(cap: usize) -> Self {
+        let buf = Box::into_raw(vec![0u8; cap].into_boxed_slice()) as *mut u8;
+        Self {
+            buf,
+            cap,
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            r_closed: AtomicBool::new(false),
+            w_closed: AtomicBool::new(false),
+            reader: Waiter::default(),
+            writer: Waiter::default(),
+        }
+    }
+}
+impl Drop for Ring {
+    fn drop(&mut self) {
+        unsafe {
+            drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+                self.buf, self.cap,
+            )));
+        }
+    }
+}
+
+/// The spin-then-park waiting of a side.
+#[derive(Debug, Default)]
+struct Waiter {
+    parked: AtomicBool,
+    thread: Mutex<Option<Thread>>,
+}
+impl Waiter {
+    fn wait<F: Fn() -> bool>(&self, ready: F) {
+        for _ in 0..SPIN_COUNT {
+            if ready() {
+                return;
+            }
+            std::hint::spin_loop();
+        }
+        *self.thread.lock().unwrap_or_else(|e| e.into_inner()) = Some(std::thread::current());
+        self.parked.store(true, Ordering::Relaxed);
+        // pairs with the fence in wake()
+        fence(Ordering::SeqCst);
+        while !ready() {
+            std::thread::park();
+        }
+        self.parked.store(false, Ordering::Relaxed);
+    }
+    fn wake(&self) {
+        // pairs with the fence in wait()
+        fence(Ordering::SeqCst);
+        if self.parked.load(Ordering::Relaxed) {
+            let a = self.thread.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(thread) = a.as_ref() {
+                thread.unpark();
+            }
+        }
+    }
+}
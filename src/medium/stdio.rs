@@ -2,9 +2,13 @@
 //! The stdio stream. This is thin-wrap of [`std::io::stdin()`],
 //! [`std::io::stdout()`], [`std::io::stderr()`].
 //!
+//! The [`StdOut`] can be block-buffered by [`StdOut::with_buffer_mode()`].
+//!
 use crate::*;
 
 use std::io::{BufRead, Read, Write};
+use std::ops::ControlFlow;
+use std::sync::{Mutex, MutexGuard};
 
 //----------------------------------------------------------------------
 //{{{ impl StreamIn
@@ -33,6 +37,12 @@ impl StreamIn for StdIn {
             buf: self.0.lock().lines(),
         })
     }
+    fn for_each_line(&self, f: &mut dyn FnMut(&str) -> Result<ControlFlow<()>>) -> Result<()> {
+        for_each_line_from(&mut StdInLock(self.0.lock()), f)
+    }
+    fn read_line_into(&self, buf: &mut String) -> Result<bool> {
+        read_line_from(&mut StdInLock(self.0.lock()), buf)
+    }
 }
 
 pub struct Lines<'a> {
@@ -69,11 +79,50 @@ impl BufRead for StdInLock<'_> {
 //----------------------------------------------------------------------
 //{{{ impl StreamOut
 /// The standard output stream.
-#[derive(Debug)]
-pub struct StdOut(std::io::Stdout);
+///
+/// By default, this is line-buffered by [`std::io::Stdout`].
+/// With [`StdOut::with_buffer_mode()`], it can be block-buffered, for bulk
+/// writes to a redirected stdout.
+pub struct StdOut {
+    inner: std::io::Stdout,
+    mode: BufferMode,
+    // the block buffer of `BufferMode::Full`
+    block: Option<Mutex<Vec<u8>>>,
+}
 impl StdOut {
     pub fn with(a: std::io::Stdout) -> Self {
-        Self(a)
+        Self {
+            inner: a,
+            mode: BufferMode::Line,
+            block: None,
+        }
+    }
+    /// create with the buffering mode.
+    ///
+    /// With `BufferMode::Full`, the data is buffered up to 64KiB, and it is
+    /// written on flush, or on drop. The error of the flush on drop is
+    /// ignored, and the buffered data is lost; call `flush_line()` before
+    /// dropping to get the error.
+    pub fn with_buffer_mode(mode: BufferMode) -> Self {
+        let block = match mode {
+            BufferMode::Full => Some(Mutex::new(Vec::with_capacity(BLOCK_SZ))),
+            _ => None,
+        };
+        Self {
+            inner: std::io::stdout(),
+            mode,
+            block,
+        }
+    }
+}
+impl std::fmt::Debug for StdOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut a = f.debug_tuple("StdOut");
+        a.field(&self.inner);
+        if self.mode != BufferMode::Line {
+            a.field(&self.mode);
+        }
+        a.finish()
     }
 }
 impl Default for StdOut {
@@ -81,9 +130,25 @@ impl Default for StdOut {
         Self::with(std::io::stdout())
     }
 }
+impl Drop for StdOut {
+    fn drop(&mut self) {
+        if self.block.is_some() {
+            let _ = self.lock().flush();
+        }
+    }
+}
 impl StreamOut for StdOut {
     fn lock(&self) -> Box<dyn StreamOutLock + '_> {
-        Box::new(StdOutLock(self.0.lock()))
+        // the order of locks: the block buffer, and then stdout
+        let block = self
+            .block
+            .as_ref()
+            .map(|a| a.lock().unwrap_or_else(|e| e.into_inner()));
+        Box::new(StdOutLock {
+            inner: self.inner.lock(),
+            mode: self.mode,
+            block,
+        })
     }
     fn is_line_pipe(&self) -> bool {
         false
@@ -91,27 +156,62 @@ impl StreamOut for StdOut {
     fn write_line(&self, string: String) -> Result<()> {
         self.lock().write_fmt(format_args!("{string}\n"))
     }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        write_lines_to(&mut *self.lock(), lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        write_lines_to(&mut *self.lock(), [string])
+    }
     fn flush_line(&self) -> Result<()> {
         self.lock().flush()
     }
 }
 
+const BLOCK_SZ: usize = 64 * 1024;
+
 /// A locked reference to `StdOut`
-pub struct StdOutLock<'a>(std::io::StdoutLock<'a>);
+pub struct StdOutLock<'a> {
+    inner: std::io::StdoutLock<'a>,
+    mode: BufferMode,
+    block: Option<MutexGuard<'a, Vec<u8>>>,
+}
 impl StreamOutLock for StdOutLock<'_> {
     #[inline(always)]
     fn buffer(&self) -> &[u8] {
-        b""
+        match self.block.as_ref() {
+            Some(a) => a.as_slice(),
+            None => b"",
+        }
     }
 }
 impl Write for StdOutLock<'_> {
-    #[inline(always)]
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        self.0.write(buf)
+        if let Some(block) = self.block.as_mut() {
+            if block.len() + buf.len() > BLOCK_SZ {
+                self.inner.write_all(block)?;
+                block.clear();
+            }
+            if buf.len() >= BLOCK_SZ {
+                // a large write does not go through the block buffer
+                return self.inner.write(buf);
+            }
+            block.extend_from_slice(buf);
+            return Ok(buf.len());
+        }
+        let r = self.inner.write(buf);
+        if self.mode == BufferMode::Unbuffered {
+            self.inner.flush()?;
+        }
+        r
     }
-    #[inline(always)]
     fn flush(&mut self) -> std::io::Result<()> {
-        self.0.flush()
+        if let Some(block) = self.block.as_mut() {
+            if !block.is_empty() {
+                self.inner.write_all(block)?;
+                block.clear();
+            }
+        }
+        self.inner.flush()
     }
 }
 //}}}
@@ -142,6 +242,12 @@ impl StreamErr for StdErr {
     fn write_line(&self, string: String) -> Result<()> {
         self.lock().write_fmt(format_args!("{string}\n"))
     }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        write_lines_to(&mut *self.lock(), lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        write_lines_to(&mut *self.lock(), [string])
+    }
     fn flush_line(&self) -> Result<()> {
         self.lock().flush()
     }
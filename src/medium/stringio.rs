@@ -4,6 +4,7 @@
 use crate::*;
 
 use std::io::{BufRead, BufReader, Read, Write};
+use std::ops::ControlFlow;
 use std::sync::{Mutex, MutexGuard};
 
 //----------------------------------------------------------------------
@@ -31,6 +32,12 @@ impl StreamIn for StringIn {
         let b = a.lines();
         Box::new(Lines { buf: b })
     }
+    fn for_each_line(&self, f: &mut dyn FnMut(&str) -> Result<ControlFlow<()>>) -> Result<()> {
+        for_each_line_from(&mut StringInLock(self.0.lock()), f)
+    }
+    fn read_line_into(&self, buf: &mut String) -> Result<bool> {
+        read_line_from(&mut StringInLock(self.0.lock()), buf)
+    }
 }
 
 /// A locked reference to `StringIn`
@@ -70,6 +77,12 @@ impl StreamOut for StringOut {
     fn write_line(&self, string: String) -> Result<()> {
         self.lock().write_fmt(format_args!("{}\n", string))
     }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        write_lines_to(&mut *self.lock(), lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        write_lines_to(&mut *self.lock(), [string])
+    }
     fn flush_line(&self) -> Result<()> {
         self.lock().flush()
     }
@@ -112,6 +125,12 @@ impl StreamErr for StringErr {
     fn write_line(&self, string: String) -> Result<()> {
         self.lock().write_fmt(format_args!("{}\n", string))
     }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        write_lines_to(&mut *self.lock(), lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        write_lines_to(&mut *self.lock(), [string])
+    }
     fn flush_line(&self) -> Result<()> {
         self.lock().flush()
     }
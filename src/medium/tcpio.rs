@@ -0,0 +1,384 @@
+//!
+//! The tcp stream. This is thin-wrap of [`std::net::TcpStream`].
+//!
+use crate::*;
+
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::ops::ControlFlow;
+use std::sync::{Mutex, MutexGuard};
+
+//----------------------------------------------------------------------
+/// split a connected `stream` and return ([`TcpOut`], [`TcpIn`]).
+///
+/// [`TcpOut`]: TcpOut
+/// [`TcpIn`]: TcpIn
+///
+pub fn split(stream: TcpStream) -> Result<(TcpOut, TcpIn)> {
+    let a = stream.try_clone()?;
+    Ok((TcpOut::with(a), TcpIn::with(stream)))
+}
+
+/// connect to `addr` and return ([`TcpOut`], [`TcpIn`]).
+///
+/// [`TcpOut`]: TcpOut
+/// [`TcpIn`]: TcpIn
+///
+pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<(TcpOut, TcpIn)> {
+    split(TcpStream::connect(addr)?)
+}
+
+//----------------------------------------------------------------------
+/// The tcp listener that yields one [`RunnelIoe`] per accepted connection.
+///
+/// The `pg_in()` and the `pg_out()` of the [`RunnelIoe`] are the connection,
+/// the `pg_err()` is [`StdErr`].
+///
+/// [`RunnelIoe`]: crate::RunnelIoe
+/// [`StdErr`]: crate::medium::stdio::StdErr
+///
+/// # Examples
+///
+/// ```rust
+/// use runnel::medium::tcpio::{connect, TcpIoeListener};
+/// use runnel::*;
+///
+/// fn run(sioe: &RunnelIoe) {
+///     for line in sioe.pg_in().lines().map(|l| l.unwrap()) {
+///         sioe.pg_out().write_line(line.to_uppercase()).unwrap();
+///     }
+/// }
+///
+/// let listener = TcpIoeListener::bind("127.0.0.1:0").unwrap();
+/// let addr = listener.local_addr().unwrap();
+/// let handler = std::thread::spawn(move || {
+///     let sioe = listener.incoming().next().unwrap().unwrap();
+///     run(&sioe);
+/// });
+///
+/// let (sout, sin) = connect(addr).unwrap();
+/// sout.write_line("abcde".to_string()).unwrap();
+/// drop(sout);
+/// let mut lines_iter = sin.lines().map(|l| l.unwrap());
+/// assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+/// assert_eq!(lines_iter.next(), None);
+/// assert!(handler.join().is_ok());
+/// ```
+#[derive(Debug)]
+pub struct TcpIoeListener(TcpListener);
+impl TcpIoeListener {
+    pub fn with(a: TcpListener) -> Self {
+        Self(a)
+    }
+    /// bind to `addr`.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        Ok(Self::with(TcpListener::bind(addr)?))
+    }
+    /// Returns the local socket address of the listener.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.0.local_addr()
+    }
+    /// accept a new connection and return it as [`RunnelIoe`].
+    ///
+    /// [`RunnelIoe`]: crate::RunnelIoe
+    pub fn accept(&self) -> Result<(RunnelIoe, SocketAddr)> {
+        let (stream, addr) = self.0.accept()?;
+        Ok((ioe_with(stream)?, addr))
+    }
+    /// Returns an iterator over the connections being accepted.
+    pub fn incoming(&self) -> Incoming<'_> {
+        Incoming { listener: self }
+    }
+}
+
+/// An iterator over the connections of [`TcpIoeListener`].
+#[derive(Debug)]
+pub struct Incoming<'a> {
+    listener: &'a TcpIoeListener,
+}
+impl Iterator for Incoming<'_> {
+    type Item = Result<RunnelIoe>;
+    fn next(&mut self) -> Option<Result<RunnelIoe>> {
+        Some(self.listener.accept().map(|(sioe, _)| sioe))
+    }
+}
+
+fn ioe_with(stream: TcpStream) -> Result<RunnelIoe> {
+    let (a_out, a_in) = split(stream)?;
+    Ok(RunnelIoeBuilder::new().pg_in(a_in).pg_out(a_out).build())
+}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamIn
+/// The tcp input stream.
+#[derive(Debug)]
+pub struct TcpIn(LockableTcpIn);
+impl TcpIn {
+    pub fn with(a: TcpStream) -> Self {
+        Self(LockableTcpIn::with(a))
+    }
+}
+impl StreamIn for TcpIn {
+    fn lock_bufread(&self) -> Box<dyn BufRead + '_> {
+        Box::new(TcpInLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn lines(&self) -> Box<dyn NextLine + '_> {
+        let a = self.0.inner.lock().unwrap().take().unwrap();
+        let b = a.lines();
+        Box::new(Lines { buf: b })
+    }
+    fn for_each_line(&self, f: &mut dyn FnMut(&str) -> Result<ControlFlow<()>>) -> Result<()> {
+        for_each_line_from(&mut TcpInLock(self.0.lock()), f)
+    }
+    fn read_line_into(&self, buf: &mut String) -> Result<bool> {
+        read_line_from(&mut TcpInLock(self.0.lock()), buf)
+    }
+}
+
+/// A locked reference to `TcpIn`
+#[derive(Debug)]
+pub struct TcpInLock<'a>(LockableTcpInLock<'a>);
+impl Read for TcpInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+impl BufRead for TcpInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.0.fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.0.consume(amt)
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamOut
+/// The tcp output stream.
+///
+/// The lines by `write_line()` are sent at once, for the interactive
+/// services. The bytes by `lock()` are buffered until `flush()`.
+/// The write half of the socket is shut down on drop, the peer sees EOF.
+#[derive(Debug)]
+pub struct TcpOut(LockableTcpOut);
+impl TcpOut {
+    pub fn with(a: TcpStream) -> Self {
+        Self(LockableTcpOut::with(a))
+    }
+}
+impl StreamOut for TcpOut {
+    fn lock(&self) -> Box<dyn StreamOutLock + '_> {
+        Box::new(TcpOutLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn write_line(&self, string: String) -> Result<()> {
+        self.write_str_line(&string)
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        let mut a = self.lock();
+        write_lines_to(&mut *a, lines)?;
+        a.flush()
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        let mut a = self.lock();
+        write_lines_to(&mut *a, [string])?;
+        a.flush()
+    }
+    fn flush_line(&self) -> Result<()> {
+        self.lock().flush()
+    }
+}
+
+/// A locked reference to `TcpOut`
+#[derive(Debug)]
+pub struct TcpOutLock<'a>(LockableTcpOutLock<'a>);
+impl StreamOutLock for TcpOutLock<'_> {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        self.0.buffer()
+    }
+}
+impl Write for TcpOutLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamErr
+/// The tcp error stream.
+///
+/// The lines by `write_line()` are sent at once, like [`TcpOut`].
+/// The write half of the socket is shut down on drop, the peer sees EOF.
+#[derive(Debug)]
+pub struct TcpErr(LockableTcpOut);
+impl TcpErr {
+    pub fn with(a: TcpStream) -> Self {
+        Self(LockableTcpOut::with(a))
+    }
+}
+impl StreamErr for TcpErr {
+    fn lock(&self) -> Box<dyn StreamErrLock + '_> {
+        Box::new(TcpErrLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn write_line(&self, string: String) -> Result<()> {
+        self.write_str_line(&string)
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        let mut a = self.lock();
+        write_lines_to(&mut *a, lines)?;
+        a.flush()
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        let mut a = self.lock();
+        write_lines_to(&mut *a, [string])?;
+        a.flush()
+    }
+    fn flush_line(&self) -> Result<()> {
+        self.lock().flush()
+    }
+}
+
+impl std::convert::From<TcpOut> for TcpErr {
+    #[inline(always)]
+    fn from(a: TcpOut) -> Self {
+        Self(a.0)
+    }
+}
+
+/// A locked reference to `TcpErr`
+#[derive(Debug)]
+pub struct TcpErrLock<'a>(LockableTcpOutLock<'a>);
+impl StreamErrLock for TcpErrLock<'_> {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        self.0.buffer()
+    }
+}
+impl Write for TcpErrLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+const LINE_BUF_SIZE: usize = 8 * 1024;
+
+#[derive(Debug)]
+struct LockableTcpIn {
+    inner: Mutex<Option<BufReader<TcpStream>>>,
+}
+impl LockableTcpIn {
+    pub fn with(a: TcpStream) -> Self {
+        LockableTcpIn {
+            inner: Mutex::new(Some(BufReader::with_capacity(LINE_BUF_SIZE, a))),
+        }
+    }
+    pub fn lock(&self) -> LockableTcpInLock<'_> {
+        LockableTcpInLock {
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LockableTcpInLock<'a> {
+    inner: MutexGuard<'a, Option<BufReader<TcpStream>>>,
+}
+impl Read for LockableTcpInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.as_mut().unwrap().read(buf)
+    }
+}
+impl BufRead for LockableTcpInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.as_mut().unwrap().fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.inner.as_mut().unwrap().consume(amt)
+    }
+}
+
+#[derive(Debug)]
+struct LockableTcpOut {
+    inner: Mutex<BufWriter<TcpStream>>,
+}
+impl LockableTcpOut {
+    fn with(a: TcpStream) -> Self {
+        LockableTcpOut {
+            inner: Mutex::new(BufWriter::with_capacity(LINE_BUF_SIZE, a)),
+        }
+    }
+    pub fn lock(&self) -> LockableTcpOutLock<'_> {
+        LockableTcpOutLock {
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+impl Drop for LockableTcpOut {
+    fn drop(&mut self) {
+        let a = self.inner.get_mut().unwrap_or_else(|e| e.into_inner());
+        let _ = a.flush();
+        // the read half may be still alive, so shut down explicitly
+        let _ = a.get_ref().shutdown(Shutdown::Write);
+    }
+}
+
+#[derive(Debug)]
+struct LockableTcpOutLock<'a> {
+    inner: MutexGuard<'a, BufWriter<TcpStream>>,
+}
+impl LockableTcpOutLock<'_> {
+    #[inline(always)]
+    pub fn buffer(&self) -> &[u8] {
+        self.inner.buffer()
+    }
+}
+impl Write for LockableTcpOutLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.inner.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub struct Lines {
+    buf: std::io::Lines<BufReader<TcpStream>>,
+}
+impl Iterator for Lines {
+    type Item = Result<String>;
+    fn next(&mut self) -> Option<Result<String>> {
+        self.buf.next()
+    }
+}
+impl NextLine for Lines {}
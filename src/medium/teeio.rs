@@ -0,0 +1,334 @@
+//!
+//! The tee stream. This fans writes out to several streams, like `tee(1)`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use runnel::medium::stdio::StdOut;
+//! use runnel::medium::stringio::StringOut;
+//! use runnel::medium::teeio::TeeOut;
+//! use runnel::*;
+//!
+//! let tee = TeeOut::with(vec![
+//!     Box::new(StdOut::default()),
+//!     Box::new(StringOut::default()),
+//! ]);
+//! tee.write_line("ABCDE".to_string()).unwrap();
+//! tee.flush_line().unwrap();
+//! assert_eq!(tee.branch(1).unwrap().lock().buffer_to_string(), "ABCDE\n");
+//! ```
+//!
+use crate::*;
+
+use std::io::Write;
+use std::sync::{Mutex, MutexGuard};
+
+//----------------------------------------------------------------------
+/// The policy for a branch that fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TeePolicy {
+    /// return the error at once, the rest branches are not written.
+    #[default]
+    FailFast,
+    /// ignore the error, and keep writing to the branch.
+    Ignore,
+    /// drop the branch, it is not written any more. After all the branches
+    /// are dropped, the writes return `ErrorKind::BrokenPipe`.
+    DropBranch,
+}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamOut
+/// The tee output stream.
+#[derive(Debug)]
+pub struct TeeOut(LockableTee<Box<dyn StreamOut>>);
+impl TeeOut {
+    /// create with `branches` and [`TeePolicy::FailFast`].
+    pub fn with(branches: Vec<Box<dyn StreamOut>>) -> Self {
+        Self::with_policy(branches, TeePolicy::default())
+    }
+    /// create with `branches` and `policy`.
+    pub fn with_policy(branches: Vec<Box<dyn StreamOut>>, policy: TeePolicy) -> Self {
+        Self(LockableTee::with(branches, policy))
+    }
+    /// Returns a reference to the branch at `idx`, even if it is dropped.
+    pub fn branch(&self, idx: usize) -> Option<&dyn StreamOut> {
+        self.0.branches.get(idx).map(|a| a.as_ref())
+    }
+    /// Returns true if the branch at `idx` is not dropped.
+    pub fn is_branch_alive(&self, idx: usize) -> bool {
+        self.0.is_alive(idx)
+    }
+}
+impl StreamOut for TeeOut {
+    fn lock(&self) -> Box<dyn StreamOutLock + '_> {
+        Box::new(TeeOutLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn write_line(&self, string: String) -> Result<()> {
+        self.0.lock().write_line(string)
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        self.0.lock().write_lines(lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        self.0.lock().write_str_line(string)
+    }
+    fn flush_line(&self) -> Result<()> {
+        self.0.lock().flush_line()
+    }
+}
+
+/// A locked reference to `TeeOut`
+#[derive(Debug)]
+pub struct TeeOutLock<'a>(LockableTeeLock<'a, Box<dyn StreamOut>>);
+impl StreamOutLock for TeeOutLock<'_> {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        b""
+    }
+}
+impl Write for TeeOutLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamErr
+/// The tee error stream.
+#[derive(Debug)]
+pub struct TeeErr(LockableTee<Box<dyn StreamErr>>);
+impl TeeErr {
+    /// create with `branches` and [`TeePolicy::FailFast`].
+    pub fn with(branches: Vec<Box<dyn StreamErr>>) -> Self {
+        Self::with_policy(branches, TeePolicy::default())
+    }
+    /// create with `branches` and `policy`.
+    pub fn with_policy(branches: Vec<Box<dyn StreamErr>>, policy: TeePolicy) -> Self {
+        Self(LockableTee::with(branches, policy))
+    }
+    /// Returns a reference to the branch at `idx`, even if it is dropped.
+    pub fn branch(&self, idx: usize) -> Option<&dyn StreamErr> {
+        self.0.branches.get(idx).map(|a| a.as_ref())
+    }
+    /// Returns true if the branch at `idx` is not dropped.
+    pub fn is_branch_alive(&self, idx: usize) -> bool {
+        self.0.is_alive(idx)
+    }
+}
+impl StreamErr for TeeErr {
+    fn lock(&self) -> Box<dyn StreamErrLock + '_> {
+        Box::new(TeeErrLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn write_line(&self, string: String) -> Result<()> {
+        self.0.lock().write_line(string)
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        self.0.lock().write_lines(lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        self.0.lock().write_str_line(string)
+    }
+    fn flush_line(&self) -> Result<()> {
+        self.0.lock().flush_line()
+    }
+}
+
+/// A locked reference to `TeeErr`
+#[derive(Debug)]
+pub struct TeeErrLock<'a>(LockableTeeLock<'a, Box<dyn StreamErr>>);
+impl StreamErrLock for TeeErrLock<'_> {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        b""
+    }
+}
+impl Write for TeeErrLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+trait TeeBranch {
+    fn write_all(&self, buf: &[u8]) -> Result<()>;
+    fn flush(&self) -> Result<()>;
+    fn write_line(&self, string: String) -> Result<()>;
+    fn write_lines(&self, lines: Vec<String>) -> Result<()>;
+    fn write_str_line(&self, string: &str) -> Result<()>;
+    fn flush_line(&self) -> Result<()>;
+}
+impl TeeBranch for Box<dyn StreamOut> {
+    #[inline(always)]
+    fn write_all(&self, buf: &[u8]) -> Result<()> {
+        self.lock().write_all(buf)
+    }
+    #[inline(always)]
+    fn flush(&self) -> Result<()> {
+        self.lock().flush()
+    }
+    #[inline(always)]
+    fn write_line(&self, string: String) -> Result<()> {
+        self.as_ref().write_line(string)
+    }
+    #[inline(always)]
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        self.as_ref().write_lines(lines)
+    }
+    #[inline(always)]
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        self.as_ref().write_str_line(string)
+    }
+    #[inline(always)]
+    fn flush_line(&self) -> Result<()> {
+        self.as_ref().flush_line()
+    }
+}
+impl TeeBranch for Box<dyn StreamErr> {
+    #[inline(always)]
+    fn write_all(&self, buf: &[u8]) -> Result<()> {
+        self.lock().write_all(buf)
+    }
+    #[inline(always)]
+    fn flush(&self) -> Result<()> {
+        self.lock().flush()
+    }
+    #[inline(always)]
+    fn write_line(&self, string: String) -> Result<()> {
+        self.as_ref().write_line(string)
+    }
+    #[inline(always)]
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        self.as_ref().write_lines(lines)
+    }
+    #[inline(always)]
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        self.as_ref().write_str_line(string)
+    }
+    #[inline(always)]
+    fn flush_line(&self) -> Result<()> {
+        self.as_ref().flush_line()
+    }
+}
+
+#[derive(Debug)]
+struct LockableTee<B> {
+    branches: Vec<B>,
+    inner: Mutex<RawTee>,
+}
+impl<B: TeeBranch> LockableTee<B> {
+    fn with(branches: Vec<B>, policy: TeePolicy) -> Self {
+        let alive = vec![true; branches.len()];
+        LockableTee {
+            branches,
+            inner: Mutex::new(RawTee { alive, policy }),
+        }
+    }
+    fn is_alive(&self, idx: usize) -> bool {
+        let a = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        a.alive.get(idx).copied().unwrap_or(false)
+    }
+    pub fn lock(&self) -> LockableTeeLock<'_, B> {
+        LockableTeeLock {
+            branches: &self.branches,
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RawTee {
+    alive: Vec<bool>,
+    policy: TeePolicy,
+}
+
+#[derive(Debug)]
+struct LockableTeeLock<'a, B> {
+    branches: &'a [B],
+    inner: MutexGuard<'a, RawTee>,
+}
+impl<B: TeeBranch> LockableTeeLock<'_, B> {
+    fn for_each<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&B, bool) -> Result<()>,
+    {
+        let last = self.inner.alive.iter().rposition(|&a| a);
+        for (idx, branch) in self.branches.iter().enumerate() {
+            if !self.inner.alive[idx] {
+                continue;
+            }
+            if let Err(err) = f(branch, Some(idx) == last) {
+                match self.inner.policy {
+                    TeePolicy::FailFast => return Err(err),
+                    TeePolicy::Ignore => (),
+                    TeePolicy::DropBranch => self.inner.alive[idx] = false,
+                }
+            }
+        }
+        if !self.branches.is_empty() && !self.inner.alive.iter().any(|&a| a) {
+            // the data goes nowhere
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "all the branches are dropped",
+            ));
+        }
+        Ok(())
+    }
+    fn write_line(&mut self, string: String) -> Result<()> {
+        let mut string = Some(string);
+        self.for_each(|branch, is_last| {
+            // the last branch takes the string without cloning
+            let s = if is_last {
+                string.take().unwrap_or_default()
+            } else {
+                string.clone().unwrap_or_default()
+            };
+            branch.write_line(s)
+        })
+    }
+    fn write_lines(&mut self, lines: Vec<String>) -> Result<()> {
+        let mut lines = Some(lines);
+        self.for_each(|branch, is_last| {
+            // the last branch takes the lines without cloning
+            let v = if is_last {
+                lines.take().unwrap_or_default()
+            } else {
+                lines.clone().unwrap_or_default()
+            };
+            branch.write_lines(v)
+        })
+    }
+    fn write_str_line(&mut self, string: &str) -> Result<()> {
+        self.for_each(|branch, _| branch.write_str_line(string))
+    }
+    fn flush_line(&mut self) -> Result<()> {
+        self.for_each(|branch, _| branch.flush_line())
+    }
+}
+impl<B: TeeBranch> Write for LockableTeeLock<'_, B> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.for_each(|branch, _| branch.write_all(buf))?;
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.for_each(|branch, _| branch.flush())
+    }
+}
@@ -0,0 +1,335 @@
+//!
+//! The unix domain socket stream. This is thin-wrap of
+//! [`std::os::unix::net::UnixStream`].
+//!
+use crate::*;
+
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::net::Shutdown;
+use std::ops::ControlFlow;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+//----------------------------------------------------------------------
+/// split a connected `stream` and return ([`UnixStreamOut`], [`UnixStreamIn`]).
+///
+/// [`UnixStreamOut`]: UnixStreamOut
+/// [`UnixStreamIn`]: UnixStreamIn
+///
+pub fn split(stream: UnixStream) -> Result<(UnixStreamOut, UnixStreamIn)> {
+    let a = stream.try_clone()?;
+    Ok((UnixStreamOut::with(a), UnixStreamIn::with(stream)))
+}
+
+/// connect to the socket at `path` and return ([`UnixStreamOut`], [`UnixStreamIn`]).
+///
+/// [`UnixStreamOut`]: UnixStreamOut
+/// [`UnixStreamIn`]: UnixStreamIn
+///
+pub fn connect<P: AsRef<Path>>(path: P) -> Result<(UnixStreamOut, UnixStreamIn)> {
+    split(UnixStream::connect(path)?)
+}
+
+/// create a full-duplex pair of [`RunnelIoe`] from [`UnixStream::pair()`].
+///
+/// The `pg_in()` of each one reads what the `pg_out()` of the other one writes.
+/// The `pg_err()` of both is [`StdErr`].
+///
+/// [`RunnelIoe`]: crate::RunnelIoe
+/// [`StdErr`]: crate::medium::stdio::StdErr
+///
+/// # Examples
+///
+/// ```rust
+/// use runnel::medium::unixsock::ioe_pair;
+///
+/// let (sioe_a, sioe_b) = ioe_pair().unwrap();
+/// let handler = std::thread::spawn(move || {
+///     for line in sioe_b.pg_in().lines().map(|l| l.unwrap()) {
+///         sioe_b.pg_out().write_line(line).unwrap();
+///         sioe_b.pg_out().flush_line().unwrap();
+///     }
+/// });
+/// sioe_a.pg_out().write_line("ABCDE".to_string()).unwrap();
+/// sioe_a.pg_out().flush_line().unwrap();
+/// {
+///     let mut lines_iter = sioe_a.pg_in().lines().map(|l| l.unwrap());
+///     assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+/// }
+/// drop(sioe_a);
+/// assert!(handler.join().is_ok());
+/// ```
+pub fn ioe_pair() -> Result<(RunnelIoe, RunnelIoe)> {
+    let (a, b) = UnixStream::pair()?;
+    let (a_out, a_in) = split(a)?;
+    let (b_out, b_in) = split(b)?;
+    Ok((
+        RunnelIoeBuilder::new().pg_in(a_in).pg_out(a_out).build(),
+        RunnelIoeBuilder::new().pg_in(b_in).pg_out(b_out).build(),
+    ))
+}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamIn
+/// The unix domain socket input stream.
+#[derive(Debug)]
+pub struct UnixStreamIn(LockableUnixStreamIn);
+impl UnixStreamIn {
+    pub fn with(a: UnixStream) -> Self {
+        Self(LockableUnixStreamIn::with(a))
+    }
+}
+impl StreamIn for UnixStreamIn {
+    fn lock_bufread(&self) -> Box<dyn BufRead + '_> {
+        Box::new(UnixStreamInLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn lines(&self) -> Box<dyn NextLine + '_> {
+        let a = self.0.inner.lock().unwrap().take().unwrap();
+        let b = a.lines();
+        Box::new(Lines { buf: b })
+    }
+    fn for_each_line(&self, f: &mut dyn FnMut(&str) -> Result<ControlFlow<()>>) -> Result<()> {
+        for_each_line_from(&mut UnixStreamInLock(self.0.lock()), f)
+    }
+    fn read_line_into(&self, buf: &mut String) -> Result<bool> {
+        read_line_from(&mut UnixStreamInLock(self.0.lock()), buf)
+    }
+}
+
+/// A locked reference to `UnixStreamIn`
+#[derive(Debug)]
+pub struct UnixStreamInLock<'a>(LockableUnixStreamInLock<'a>);
+impl Read for UnixStreamInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+impl BufRead for UnixStreamInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.0.fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.0.consume(amt)
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamOut
+/// The unix domain socket output stream.
+///
+/// The write half of the socket is shut down on drop, the peer sees EOF.
+#[derive(Debug)]
+pub struct UnixStreamOut(LockableUnixStreamOut);
+impl UnixStreamOut {
+    pub fn with(a: UnixStream) -> Self {
+        Self(LockableUnixStreamOut::with(a))
+    }
+}
+impl StreamOut for UnixStreamOut {
+    fn lock(&self) -> Box<dyn StreamOutLock + '_> {
+        Box::new(UnixStreamOutLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn write_line(&self, string: String) -> Result<()> {
+        self.lock().write_fmt(format_args!("{}\n", string))
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        write_lines_to(&mut *self.lock(), lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        write_lines_to(&mut *self.lock(), [string])
+    }
+    fn flush_line(&self) -> Result<()> {
+        self.lock().flush()
+    }
+}
+
+/// A locked reference to `UnixStreamOut`
+#[derive(Debug)]
+pub struct UnixStreamOutLock<'a>(LockableUnixStreamOutLock<'a>);
+impl StreamOutLock for UnixStreamOutLock<'_> {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        self.0.buffer()
+    }
+}
+impl Write for UnixStreamOutLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+//{{{ impl StreamErr
+/// The unix domain socket error stream.
+///
+/// The write half of the socket is shut down on drop, the peer sees EOF.
+#[derive(Debug)]
+pub struct UnixStreamErr(LockableUnixStreamOut);
+impl UnixStreamErr {
+    pub fn with(a: UnixStream) -> Self {
+        Self(LockableUnixStreamOut::with(a))
+    }
+}
+impl StreamErr for UnixStreamErr {
+    fn lock(&self) -> Box<dyn StreamErrLock + '_> {
+        Box::new(UnixStreamErrLock(self.0.lock()))
+    }
+    fn is_line_pipe(&self) -> bool {
+        false
+    }
+    fn write_line(&self, string: String) -> Result<()> {
+        self.lock().write_fmt(format_args!("{}\n", string))
+    }
+    fn write_lines(&self, lines: Vec<String>) -> Result<()> {
+        write_lines_to(&mut *self.lock(), lines)
+    }
+    fn write_str_line(&self, string: &str) -> Result<()> {
+        write_lines_to(&mut *self.lock(), [string])
+    }
+    fn flush_line(&self) -> Result<()> {
+        self.lock().flush()
+    }
+}
+
+impl std::convert::From<UnixStreamOut> for UnixStreamErr {
+    #[inline(always)]
+    fn from(a: UnixStreamOut) -> Self {
+        Self(a.0)
+    }
+}
+
+/// A locked reference to `UnixStreamErr`
+#[derive(Debug)]
+pub struct UnixStreamErrLock<'a>(LockableUnixStreamOutLock<'a>);
+impl StreamErrLock for UnixStreamErrLock<'_> {
+    #[inline(always)]
+    fn buffer(&self) -> &[u8] {
+        self.0.buffer()
+    }
+}
+impl Write for UnixStreamErrLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+//}}}
+
+//----------------------------------------------------------------------
+const LINE_BUF_SIZE: usize = 8 * 1024;
+
+#[derive(Debug)]
+struct LockableUnixStreamIn {
+    inner: Mutex<Option<BufReader<UnixStream>>>,
+}
+impl LockableUnixStreamIn {
+    pub fn with(a: UnixStream) -> Self {
+        LockableUnixStreamIn {
+            inner: Mutex::new(Some(BufReader::with_capacity(LINE_BUF_SIZE, a))),
+        }
+    }
+    pub fn lock(&self) -> LockableUnixStreamInLock<'_> {
+        LockableUnixStreamInLock {
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LockableUnixStreamInLock<'a> {
+    inner: MutexGuard<'a, Option<BufReader<UnixStream>>>,
+}
+impl Read for LockableUnixStreamInLock<'_> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.as_mut().unwrap().read(buf)
+    }
+}
+impl BufRead for LockableUnixStreamInLock<'_> {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.as_mut().unwrap().fill_buf()
+    }
+    #[inline(always)]
+    fn consume(&mut self, amt: usize) {
+        self.inner.as_mut().unwrap().consume(amt)
+    }
+}
+
+#[derive(Debug)]
+struct LockableUnixStreamOut {
+    inner: Mutex<BufWriter<UnixStream>>,
+}
+impl LockableUnixStreamOut {
+    fn with(a: UnixStream) -> Self {
+        LockableUnixStreamOut {
+            inner: Mutex::new(BufWriter::with_capacity(LINE_BUF_SIZE, a)),
+        }
+    }
+    pub fn lock(&self) -> LockableUnixStreamOutLock<'_> {
+        LockableUnixStreamOutLock {
+            inner: self.inner.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+impl Drop for LockableUnixStreamOut {
+    fn drop(&mut self) {
+        let a = self.inner.get_mut().unwrap_or_else(|e| e.into_inner());
+        let _ = a.flush();
+        // the read half may be still alive, so shut down explicitly
+        let _ = a.get_ref().shutdown(Shutdown::Write);
+    }
+}
+
+#[derive(Debug)]
+struct LockableUnixStreamOutLock<'a> {
+    inner: MutexGuard<'a, BufWriter<UnixStream>>,
+}
+impl LockableUnixStreamOutLock<'_> {
+    #[inline(always)]
+    pub fn buffer(&self) -> &[u8] {
+        self.inner.buffer()
+    }
+}
+impl Write for LockableUnixStreamOutLock<'_> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.inner.write(buf)
+    }
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub struct Lines {
+    buf: std::io::Lines<BufReader<UnixStream>>,
+}
+impl Iterator for Lines {
+    type Item = Result<String>;
+    fn next(&mut self) -> Option<Result<String>> {
+        self.buf.next()
+    }
+}
+impl NextLine for Lines {}
@@ -0,0 +1,129 @@
+//!
+//! The waiting mode of the channel operations, for the pipes.
+//!
+use std::sync::mpsc::{Receiver, RecvTimeoutError, SyncSender, TryRecvError, TrySendError};
+use std::time::{Duration, Instant};
+
+/// the interval of retrying to send, there is no `send_timeout()` in std.
+const RETRY_INTERVAL: Duration = Duration::from_millis(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Wait {
+    /// wait until it is done.
+    Block,
+    /// do not wait, return `WouldBlock`.
+    Try,
+    /// wait until the deadline, return `TimedOut`.
+    Until(Instant),
+}
+
+impl Wait {
+    /// wait for `dur` from now.
+    pub(crate) fn timeout(dur: Duration) -> Self {
+        match Instant::now().checked_add(dur) {
+            Some(a) => Wait::Until(a),
+            None => Wait::Block,
+        }
+    }
+    /// receive from `receiver`. `Ok(None)` means that the sender is gone.
+    pub(crate) fn recv<T>(self, receiver: &Receiver<T>) -> std::io::Result<Option<T>> {
+        match self {
+            Wait::Block => Ok(receiver.recv().ok()),
+            Wait::Try => match receiver.try_recv() {
+                Ok(a) => Ok(Some(a)),
+                Err(TryRecvError::Empty) => Err(would_block()),
+                Err(TryRecvError::Disconnected) => Ok(None),
+            },
+            Wait::Until(deadline) => {
+                let dur = deadline.saturating_duration_since(Instant::now());
+                match receiver.recv_timeout(dur) {
+                    Ok(a) => Ok(Some(a)),
+                    Err(RecvTimeoutError::Timeout) => Err(timed_out()),
+                    Err(RecvTimeoutError::Disconnected) => Ok(None),
+                }
+            }
+        }
+    }
+    /// send `a` to `sender`. On error, `a` is returned with the error.
+    pub(crate) fn send<T>(
+        self,
+        sender: &Sender<T>,
+        a: T,
+    ) -> std::result::Result<(), (T, std::io::Error)> {
+        let sender = match sender {
+            Sender::Bounded(sender) => sender,
+            Sender::Unbounded(sender) => {
+                // this does not wait
+                return sender.send(a).map_err(|err| (err.0, disconnected()));
+            }
+        };
+        let mut a = a;
+        loop {
+            match sender.try_send(a) {
+                Ok(()) => return Ok(()),
+                Err(TrySendError::Disconnected(b)) => return Err((b, disconnected())),
+                Err(TrySendError::Full(b)) => a = b,
+            }
+            match self {
+                Wait::Block => {
+                    return match sender.send(a) {
+                        Ok(()) => Ok(()),
+                        Err(err) => Err((err.0, disconnected())),
+                    };
+                }
+                Wait::Try => return Err((a, would_block())),
+                Wait::Until(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err((a, timed_out()));
+                    }
+                    std::thread::sleep(RETRY_INTERVAL.min(deadline - now));
+                }
+            }
+        }
+    }
+}
+
+/// The sender of the channel, that is bounded by the message count or not.
+///
+/// The unbounded one is used with the byte budget.
+#[derive(Debug)]
+pub(crate) enum Sender<T> {
+    Bounded(SyncSender<T>),
+    Unbounded(std::sync::mpsc::Sender<T>),
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Sender::Bounded(a) => Sender::Bounded(a.clone()),
+            Sender::Unbounded(a) => Sender::Unbounded(a.clone()),
+        }
+    }
+}
+
+pub(crate) fn would_block() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::WouldBlock, "the pipe is not ready")
+}
+
+pub(crate) fn timed_out() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::TimedOut, "the pipe timed out")
+}
+
+fn disconnected() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::BrokenPipe, "the reader is gone")
+}
+
+/// Returns true if `err` is of the pipe not ready, the data is kept and
+/// can be sent later.
+pub(crate) fn is_not_ready(err: &std::io::Error) -> bool {
+    matches!(
+        err.kind(),
+        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+    )
+}
+
+/// the error of writing to the closed pipe.
+pub(crate) fn closed() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::BrokenPipe, "the pipe is closed")
+}
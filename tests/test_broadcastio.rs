@@ -0,0 +1,268 @@
+#[cfg(test)]
+mod test_stream_broadcastio {
+    use runnel::medium::broadcastio::*;
+    use runnel::*;
+    use std::io::{Read, Write};
+    #[test]
+    fn test_pipe_all_readers() {
+        let (sout, sin_a) = broadcast_pipe(2, BroadcastPolicy::SlowestGoverns);
+        let readers = vec![sin_a, sout.subscribe(), sout.subscribe()];
+        let handler = std::thread::spawn(move || {
+            for i in 0..1000 {
+                #[rustfmt::skip]
+                let res = sout.lock()
+                    .write_fmt(format_args!("{}\n", i));
+                assert!(res.is_ok());
+                assert!(sout.flush_line().is_ok());
+            }
+        });
+        let handlers: Vec<_> = readers
+            .into_iter()
+            .map(|sin| {
+                std::thread::spawn(move || {
+                    let mut lines_iter = sin.lines().map(|l| l.unwrap());
+                    for i in 0..1000 {
+                        assert_eq!(lines_iter.next(), Some(format!("{}", i)));
+                    }
+                    assert_eq!(lines_iter.next(), None);
+                })
+            })
+            .collect();
+        assert!(handler.join().is_ok());
+        for h in handlers {
+            assert!(h.join().is_ok());
+        }
+    }
+    #[test]
+    fn test_pipe_slowest_governs() {
+        let (sout, sin) = broadcast_pipe(1, BroadcastPolicy::SlowestGoverns);
+        let handler = std::thread::spawn(move || {
+            for i in 0..3 {
+                sout.write_line(format!("{}", i)).unwrap();
+                sout.flush_line().unwrap();
+            }
+        });
+        // the writer waits for this reader
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(!handler.is_finished());
+        let mut s = String::new();
+        assert!(sin.lock_bufread().read_to_string(&mut s).is_ok());
+        assert_eq!(s, "0\n1\n2\n");
+        assert_eq!(sin.dropped_chunks(), 0);
+        assert!(handler.join().is_ok());
+    }
+    #[test]
+    fn test_pipe_lag_and_drop() {
+        let (sout, sin) = broadcast_pipe(2, BroadcastPolicy::LagAndDrop);
+        for i in 0..5 {
+            sout.write_line(format!("{}", i)).unwrap();
+            sout.flush_line().unwrap();
+        }
+        drop(sout);
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("3")));
+        assert_eq!(lines_iter.next(), Some(String::from("4")));
+        assert_eq!(lines_iter.next(), None);
+    }
+    #[test]
+    fn test_pipe_subscribe_later() {
+        let (sout, sin_a) = broadcast_pipe(4, BroadcastPolicy::SlowestGoverns);
+        sout.write_line("ABCDE".to_string()).unwrap();
+        sout.flush_line().unwrap();
+        let sin_b = sout.subscribe();
+        sout.write_line("efgh".to_string()).unwrap();
+        sout.flush_line().unwrap();
+        drop(sout);
+        let mut lines_iter = sin_a.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(lines_iter.next(), None);
+        let mut lines_iter = sin_b.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(lines_iter.next(), None);
+    }
+    #[test]
+    fn test_pipe_no_reader() {
+        let (sout, sin) = broadcast_pipe(1, BroadcastPolicy::SlowestGoverns);
+        drop(sin);
+        sout.write_line("ABCDE".to_string()).unwrap();
+        let r = sout.flush_line();
+        assert_eq!(r.unwrap_err().kind(), std::io::ErrorKind::BrokenPipe);
+        assert_eq!(sout.lock().buffer_to_string(), "ABCDE\n");
+    }
+    #[test]
+    fn test_pipe_reader_drop_unblocks_writer() {
+        let (sout, sin_a) = broadcast_pipe(1, BroadcastPolicy::SlowestGoverns);
+        let sin_b = sout.subscribe();
+        let handler = std::thread::spawn(move || {
+            for i in 0..100 {
+                sout.write_line(format!("{}", i)).unwrap();
+                sout.flush_line().unwrap();
+            }
+        });
+        drop(sin_b);
+        let mut lines_iter = sin_a.lines().map(|l| l.unwrap());
+        for i in 0..100 {
+            assert_eq!(lines_iter.next(), Some(format!("{}", i)));
+        }
+        assert_eq!(lines_iter.next(), None);
+        assert!(handler.join().is_ok());
+    }
+    #[test]
+    fn test_line_pipe_all_readers() {
+        let (sout, sin_a) = line_broadcast_pipe(2, BroadcastPolicy::SlowestGoverns);
+        let readers = vec![sin_a, sout.subscribe()];
+        let handler = std::thread::spawn(move || {
+            for i in 0..3000 {
+                assert!(sout.write_line(format!("{}", i)).is_ok());
+            }
+            assert!(sout.flush_line().is_ok());
+        });
+        let handlers: Vec<_> = readers
+            .into_iter()
+            .map(|sin| {
+                std::thread::spawn(move || {
+                    let mut lines_iter = sin.lines().map(|l| l.unwrap());
+                    for i in 0..3000 {
+                        assert_eq!(lines_iter.next(), Some(format!("{}", i)));
+                    }
+                    assert_eq!(lines_iter.next(), None);
+                })
+            })
+            .collect();
+        assert!(handler.join().is_ok());
+        for h in handlers {
+            assert!(h.join().is_ok());
+        }
+    }
+    #[test]
+    fn test_line_pipe_lag_and_drop() {
+        let (sout, sin) = line_broadcast_pipe(1, BroadcastPolicy::LagAndDrop);
+        let serr = LineBroadcastPipeErr::from(sout);
+        for i in 0..3 {
+            #[rustfmt::skip]
+            let res = serr.lock()
+                .write_fmt(format_args!("{}\n", i));
+            assert!(res.is_ok());
+            assert!(serr.flush_line().is_ok());
+        }
+        drop(serr);
+        let mut s = String::new();
+        assert!(sin.lock_bufread().read_to_string(&mut s).is_ok());
+        assert_eq!(s, "2\n");
+        assert_eq!(sin.dropped_chunks(), 2);
+    }
+    #[test]
+    fn test_pipe_write_lines() {
+        let (sout, sin) = broadcast_pipe(2, BroadcastPolicy::SlowestGoverns);
+        let v = vec!["ABCDE".to_string(), "efgh".to_string()];
+        assert!(sout.write_lines(v).is_ok());
+        assert!(sout.write_str_line("ijk").is_ok());
+        drop(sout);
+        let mut s = String::new();
+        assert!(sin.lock_bufread().read_to_string(&mut s).is_ok());
+        assert_eq!(s, "ABCDE\nefgh\nijk\n");
+    }
+    #[test]
+    fn test_line_pipe_write_lines() {
+        let (sout, sin) = line_broadcast_pipe(2, BroadcastPolicy::SlowestGoverns);
+        let v = vec!["ABCDE".to_string(), "efgh".to_string()];
+        assert!(sout.write_lines(v).is_ok());
+        assert!(sout.write_str_line("ijk").is_ok());
+        drop(sout);
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(lines_iter.next(), Some(String::from("ijk")));
+        assert_eq!(lines_iter.next(), None);
+    }
+    #[test]
+    fn test_pipe_drop_flush() {
+        let (sout, sin) = broadcast_pipe(1, BroadcastPolicy::SlowestGoverns);
+        sout.lock().write_all(b"ABCDE\nefgh").unwrap();
+        drop(sout);
+        let mut s = String::new();
+        assert!(sin.lock_bufread().read_to_string(&mut s).is_ok());
+        assert_eq!(s, "ABCDE\nefgh");
+    }
+    #[test]
+    fn test_pipe_drop_full() {
+        let (sout, sin) = broadcast_pipe(1, BroadcastPolicy::SlowestGoverns);
+        sout.write_line("ABCDE".to_string()).unwrap();
+        sout.flush_line().unwrap();
+        sout.write_line("efgh".to_string()).unwrap();
+        let handle = std::thread::spawn(move || {
+            // the reader is alive, but stalls for a while
+            std::thread::sleep(std::time::Duration::from_millis(300));
+            let mut s = String::new();
+            assert!(sin.lock_bufread().read_to_string(&mut s).is_ok());
+            s
+        });
+        // the channel is full, the drop waits for the reader
+        drop(sout);
+        assert_eq!(handle.join().unwrap(), "ABCDE\nefgh\n");
+    }
+    #[test]
+    fn test_pipe_over_consume() {
+        use std::io::BufRead;
+        let (sout, sin) = broadcast_pipe(2, BroadcastPolicy::SlowestGoverns);
+        sout.write_line("ABCDE".to_string()).unwrap();
+        sout.flush_line().unwrap();
+        sout.write_line("efgh".to_string()).unwrap();
+        drop(sout);
+        let mut a = sin.lock_bufread();
+        assert_eq!(a.fill_buf().unwrap(), b"ABCDE\n");
+        // past the chunk, it is clamped to the chunk and does not overflow
+        a.consume(usize::MAX);
+        a.consume(1);
+        assert_eq!(a.fill_buf().unwrap(), b"efgh\n");
+    }
+    #[test]
+    fn test_line_pipe_drop_flush() {
+        let (sout, sin) = line_broadcast_pipe(1, BroadcastPolicy::SlowestGoverns);
+        sout.write_line("ABCDE".to_string()).unwrap();
+        sout.lock().write_all(b"efgh").unwrap();
+        drop(sout);
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(lines_iter.next(), None);
+    }
+}
+
+#[cfg(test)]
+mod test_stream_ioe_broadcastio {
+    use runnel::medium::broadcastio::*;
+    use runnel::medium::stringio::StringOut;
+    use runnel::*;
+    #[test]
+    fn test_ioe_broadcastio() {
+        let (sout, sin_a) = line_broadcast_pipe(4, BroadcastPolicy::default());
+        let sin_b = sout.subscribe();
+        let mut handlers = Vec::new();
+        for sin in [sin_a, sin_b] {
+            let sioe = RunnelIoeBuilder::new()
+                .pg_in(sin)
+                .pg_out(StringOut::default())
+                .build();
+            handlers.push(std::thread::spawn(move || {
+                for line in sioe.pg_in().lines().map(|l| l.unwrap()) {
+                    sioe.pg_out().write_line(line).unwrap();
+                }
+                sioe.pg_out().lock().buffer_to_string()
+            }));
+        }
+        let sioe = RunnelIoeBuilder::new()
+            .fill_stringio_with_str("ABCDE\nefgh\n")
+            .pg_out(sout)
+            .build();
+        for line in sioe.pg_in().lines().map(|l| l.unwrap()) {
+            sioe.pg_out().write_line(line).unwrap();
+        }
+        sioe.pg_out().flush_line().unwrap();
+        drop(sioe);
+        for h in handlers {
+            assert_eq!(h.join().unwrap(), "ABCDE\nefgh\n");
+        }
+    }
+}
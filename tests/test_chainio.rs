@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod test_stream_chainio {
+    use runnel::medium::chainio::*;
+    use runnel::medium::stringio::StringIn;
+    use runnel::*;
+    use std::io::Read;
+    use std::sync::{Arc, Mutex};
+    #[test]
+    fn test_in() {
+        let sin = ChainIn::with(vec![
+            Box::new(StringIn::with_str("ABCDE\nefgh\n")),
+            Box::new(StringIn::with_str("")),
+            Box::new(StringIn::with_str("1234\nijklm\n")),
+        ]);
+        assert_eq!(sin.len(), 3);
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(lines_iter.next(), Some(String::from("1234")));
+        assert_eq!(sin.current(), 2);
+        assert_eq!(lines_iter.next(), Some(String::from("ijklm")));
+        assert_eq!(lines_iter.next(), None);
+        assert_eq!(sin.current(), 3);
+    }
+    #[test]
+    fn test_in_bufread() {
+        let sin = ChainIn::with(vec![
+            Box::new(StringIn::with_str("ABCDE\nef")),
+            Box::new(StringIn::with_str("gh\n1234\n")),
+        ]);
+        let mut s = String::new();
+        let r = sin.lock_bufread().read_to_string(&mut s);
+        assert_eq!(r.unwrap(), 16);
+        assert_eq!(s, "ABCDE\nefgh\n1234\n");
+        assert_eq!(sin.current(), 2);
+    }
+    #[test]
+    fn test_in_boundary() {
+        let ended = Arc::new(Mutex::new(Vec::new()));
+        let ended_c = ended.clone();
+        let sin = ChainIn::with_boundary(
+            vec![
+                Box::new(StringIn::with_str("ABCDE\n")),
+                Box::new(StringIn::with_str("")),
+                Box::new(StringIn::with_str("efgh\n")),
+            ],
+            move |idx| ended_c.lock().unwrap().push(idx),
+        );
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(*ended.lock().unwrap(), Vec::<usize>::new());
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(*ended.lock().unwrap(), vec![0, 1]);
+        assert_eq!(lines_iter.next(), None);
+        assert_eq!(*ended.lock().unwrap(), vec![0, 1, 2]);
+        assert_eq!(lines_iter.next(), None);
+        assert_eq!(*ended.lock().unwrap(), vec![0, 1, 2]);
+    }
+    #[test]
+    fn test_in_empty() {
+        let sin = ChainIn::with(Vec::new());
+        assert!(sin.is_empty());
+        let mut lines_iter = sin.lines();
+        assert!(lines_iter.next().is_none());
+    }
+}
+
+#[cfg(test)]
+mod test_stream_ioe_chainio {
+    use runnel::medium::chainio::*;
+    use runnel::medium::stringio::{StringIn, StringOut};
+    use runnel::*;
+    use std::io::Write;
+    #[test]
+    fn test_ioe() {
+        let sioe = RunnelIoeBuilder::new()
+            .pg_in(ChainIn::with(vec![
+                Box::new(StringIn::with_str("ABCDE\n")),
+                Box::new(StringIn::with_str("efgh\n")),
+            ]))
+            .pg_out(StringOut::default())
+            .build();
+        for line in sioe.pg_in().lines().map(|l| l.unwrap()) {
+            #[rustfmt::skip]
+            let _ = sioe.pg_out().lock()
+                .write_fmt(format_args!("{}\n", line));
+        }
+        assert_eq!(sioe.pg_out().lock().buffer_to_string(), "ABCDE\nefgh\n");
+    }
+}
@@ -0,0 +1,151 @@
+#[cfg(test)]
+#[cfg(unix)]
+mod test_stream_childio {
+    use runnel::medium::childio::*;
+    use runnel::medium::stringio::StringErr;
+    use runnel::*;
+    use std::io::{BufRead, Write};
+    use std::process::Command;
+    use std::sync::Arc;
+    #[test]
+    fn test_cat() {
+        let (sout, sin) = spawn(&mut Command::new("cat")).unwrap();
+        let handler = std::thread::spawn(move || {
+            sout.write_line("ABCDE".to_string()).unwrap();
+            sout.write_line("efgh".to_string()).unwrap();
+            sout.flush_line().unwrap();
+        });
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(lines_iter.next(), None);
+        assert!(handler.join().is_ok());
+        assert!(sin.wait().unwrap().success());
+    }
+    #[test]
+    fn test_sort_bufread() {
+        let (sout, sin) = spawn(&mut Command::new("sort")).unwrap();
+        #[rustfmt::skip]
+        sout.lock().write_fmt(format_args!("efgh\nABCDE\n")).unwrap();
+        sout.close().unwrap();
+        assert!(sout.write_line("ijk".to_string()).is_err());
+        let mut s = String::new();
+        let _ = sin.lock_bufread().read_line(&mut s).unwrap();
+        assert_eq!(s, "ABCDE\n");
+        s.clear();
+        let _ = sin.lock_bufread().read_line(&mut s).unwrap();
+        assert_eq!(s, "efgh\n");
+        s.clear();
+        let _ = sin.lock_bufread().read_line(&mut s).unwrap();
+        assert_eq!(s, "");
+        assert!(sout.wait().unwrap().success());
+    }
+    #[test]
+    fn test_exit_status() {
+        let (sout, sin) = spawn(Command::new("sh").arg("-c").arg("exit 3")).unwrap();
+        drop(sout);
+        let mut lines_iter = sin.lines();
+        assert!(lines_iter.next().is_none());
+        assert_eq!(sin.wait().unwrap().code(), Some(3));
+        assert_eq!(sin.exit_status().unwrap().code(), Some(3));
+    }
+    #[test]
+    fn test_wait() {
+        let (sout, sin) = spawn(Command::new("sh").arg("-c").arg("read x; exit 5")).unwrap();
+        // the child is waiting for the input
+        assert!(sout.exit_status().is_none());
+        sout.close().unwrap();
+        assert_eq!(sin.wait().unwrap().code(), Some(5));
+        assert_eq!(sout.exit_status().unwrap().code(), Some(5));
+    }
+    #[test]
+    fn test_exit_status_while_waiting() {
+        let (sout, sin) = spawn(Command::new("sh").arg("-c").arg("read x; exit 5")).unwrap();
+        let handler = std::thread::spawn(move || sin.wait().unwrap().code());
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        // the other thread is waiting, this does not block
+        assert!(sout.exit_status().is_none());
+        sout.close().unwrap();
+        assert_eq!(handler.join().unwrap(), Some(5));
+        assert_eq!(sout.exit_status().unwrap().code(), Some(5));
+    }
+    #[test]
+    fn test_drop_running() {
+        // the child ignores the EOF of its stdin
+        let (sout, sin) = spawn(Command::new("sleep").arg("2")).unwrap();
+        let start = std::time::Instant::now();
+        drop(sout);
+        drop(sin);
+        // the drop does not wait for the running child
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+    }
+    #[test]
+    fn test_err() {
+        let serr = Arc::new(StringErr::default());
+        #[rustfmt::skip]
+        let (sout, sin) = spawn_with_err(
+            Command::new("sh").arg("-c").arg("echo ABCDE >&2; echo efgh >&2; cat"),
+            serr.clone(),
+        ).unwrap();
+        sout.write_line("ijk".to_string()).unwrap();
+        sout.close().unwrap();
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ijk")));
+        assert_eq!(lines_iter.next(), None);
+        assert!(sin.wait().unwrap().success());
+        assert_eq!(serr.lock().buffer_to_string(), "ABCDE\nefgh\n");
+    }
+    #[test]
+    fn test_stdout_closed_early() {
+        // the child closes its stdout, but keeps running
+        let (sout, sin) = spawn(Command::new("sh").arg("-c").arg("exec >&-; read x")).unwrap();
+        let mut s = String::new();
+        assert_eq!(sin.lock_bufread().read_line(&mut s).unwrap(), 0);
+        assert!(sin.exit_status().is_none());
+        sout.write_line("ABCDE".to_string()).unwrap();
+        assert!(sout.wait().unwrap().success());
+    }
+    #[test]
+    fn test_spawn_err() {
+        let serr = spawn_err(
+            Command::new("sh")
+                .arg("-c")
+                .arg(r#"read x; test "$x" = ABCDE"#),
+        )
+        .unwrap();
+        serr.write_line("ABCDE".to_string()).unwrap();
+        assert!(serr.wait().unwrap().success());
+        assert!(serr.write_line("efgh".to_string()).is_err());
+    }
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod test_stream_ioe_childio {
+    use runnel::medium::childio::*;
+    use runnel::*;
+    use std::process::Command;
+    #[test]
+    fn test_ioe_childio() {
+        let (sout, sin) = spawn(&mut Command::new("sort")).unwrap();
+        //
+        #[rustfmt::skip]
+        let sioe = RunnelIoeBuilder::new().fill_stringio_with_str("efgh\nABCDE\n")
+            .pg_out(sout).build();
+        let handler = std::thread::spawn(move || {
+            for line in sioe.pg_in().lines().map(|l| l.unwrap()) {
+                sioe.pg_out().write_line(line).unwrap();
+            }
+            sioe.pg_out().flush_line().unwrap();
+        });
+        //
+        #[rustfmt::skip]
+        let sioe = RunnelIoeBuilder::new().fill_stringio_with_str("")
+            .pg_in(sin).build();
+        let mut lines_iter = sioe.pg_in().lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(lines_iter.next(), None);
+        assert!(handler.join().is_ok());
+    }
+}
@@ -0,0 +1,138 @@
+#[cfg(test)]
+#[cfg(any(
+    all(
+        any(target_os = "linux", target_os = "android"),
+        any(
+            target_arch = "x86",
+            target_arch = "x86_64",
+            target_arch = "arm",
+            target_arch = "aarch64",
+            target_arch = "mips",
+            target_arch = "mips64",
+            target_arch = "powerpc",
+            target_arch = "powerpc64",
+            target_arch = "riscv64",
+            target_arch = "s390x",
+        )
+    ),
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly",
+))]
+mod test_stream_fifoio {
+    use runnel::medium::fifoio::*;
+    use runnel::*;
+    use std::io::{BufRead, Write};
+    use std::path::PathBuf;
+    use std::time::Duration;
+    //
+    fn tmp_fifo(name: &str) -> PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "runnel-test-fifoio-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_file(&path);
+        mkfifo(&path, 0o600).unwrap();
+        path
+    }
+    #[test]
+    fn test_mkfifo_exists() {
+        let path = tmp_fifo("exists");
+        let res = mkfifo(&path, 0o600);
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::AlreadyExists);
+        let _ = std::fs::remove_file(&path);
+    }
+    #[test]
+    fn test_in_out() {
+        let path = tmp_fifo("in_out");
+        let path_w = path.clone();
+        let handler = std::thread::spawn(move || {
+            let sout = FifoOut::open(&path_w).unwrap();
+            #[rustfmt::skip]
+            sout.lock().write_fmt(format_args!("{}\nACBDE\n", 1234)).unwrap();
+            sout.write_line("efgh".to_string()).unwrap();
+            sout.flush_line().unwrap();
+        });
+        let sin = FifoIn::open(&path).unwrap();
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("1234")));
+        assert_eq!(lines_iter.next(), Some(String::from("ACBDE")));
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(lines_iter.next(), None);
+        assert!(handler.join().is_ok());
+        let _ = std::fs::remove_file(&path);
+    }
+    #[test]
+    fn test_err_bufread() {
+        let path = tmp_fifo("err");
+        let path_w = path.clone();
+        let handler = std::thread::spawn(move || {
+            let serr = FifoErr::open(&path_w).unwrap();
+            serr.write_line("ABCDE".to_string()).unwrap();
+        });
+        let sin = FifoIn::open(&path).unwrap();
+        let mut s = String::new();
+        let _ = sin.lock_bufread().read_line(&mut s).unwrap();
+        assert_eq!(s, "ABCDE\n");
+        assert!(handler.join().is_ok());
+        let _ = std::fs::remove_file(&path);
+    }
+    #[test]
+    fn test_out_nonblocking() {
+        let path = tmp_fifo("out_nonblocking");
+        let res = FifoOut::open_nonblocking(&path);
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::WouldBlock);
+        //
+        let sin = FifoIn::open_nonblocking(&path).unwrap();
+        let sout = FifoOut::open_nonblocking(&path).unwrap();
+        sout.write_line("ABCDE".to_string()).unwrap();
+        drop(sout);
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), None);
+        let _ = std::fs::remove_file(&path);
+    }
+    #[test]
+    fn test_out_timeout() {
+        let path = tmp_fifo("out_timeout");
+        let res = FifoOut::open_timeout(&path, Duration::from_millis(50));
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+        //
+        let path_r = path.clone();
+        let handler = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            let sin = FifoIn::open(&path_r).unwrap();
+            let mut lines_iter = sin.lines().map(|l| l.unwrap());
+            assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        });
+        let sout = FifoOut::open_timeout(&path, Duration::from_secs(10)).unwrap();
+        sout.write_line("ABCDE".to_string()).unwrap();
+        drop(sout);
+        assert!(handler.join().is_ok());
+        let _ = std::fs::remove_file(&path);
+    }
+    #[test]
+    fn test_in_timeout() {
+        let path = tmp_fifo("in_timeout");
+        let res = FifoIn::open_timeout(&path, Duration::from_millis(50));
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+        //
+        let path_w = path.clone();
+        let handler = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            let sout = FifoOut::open(&path_w).unwrap();
+            sout.write_line("ABCDE".to_string()).unwrap();
+        });
+        let sin = FifoIn::open_timeout(&path, Duration::from_secs(10)).unwrap();
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), None);
+        assert!(handler.join().is_ok());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,178 @@
+#[cfg(test)]
+mod test_stream_fileio {
+    use runnel::medium::fileio::*;
+    use runnel::*;
+    use std::io::{BufRead, Write};
+    use std::path::PathBuf;
+    //
+    fn tmp_path(name: &str) -> PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "runnel-test-fileio-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+    #[test]
+    fn test_in() {
+        let path = tmp_path("in");
+        std::fs::write(&path, "ABCDE\nefgh\n").unwrap();
+        let sin = FileIn::open(&path).unwrap();
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(lines_iter.next(), None);
+        let _ = std::fs::remove_file(&path);
+    }
+    #[test]
+    fn test_in_bufread() {
+        let path = tmp_path("in_bufread");
+        std::fs::write(&path, "ABCDE\nefgh\n").unwrap();
+        let sin = FileIn::open(&path).unwrap();
+        let mut s = String::new();
+        let _ = sin.lock_bufread().read_line(&mut s).unwrap();
+        assert_eq!(s, "ABCDE\n");
+        let _ = std::fs::remove_file(&path);
+    }
+    #[test]
+    fn test_out() {
+        let path = tmp_path("out");
+        {
+            let sout = FileOut::create(&path).unwrap();
+            #[rustfmt::skip]
+            let res = sout.lock()
+                .write_fmt(format_args!("{}\nACBDE\nefgh\n", 1234));
+            assert!(res.is_ok());
+            assert!(sout.write_line("ijk".to_string()).is_ok());
+            assert!(sout.flush_line().is_ok());
+        }
+        let s = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(s, "1234\nACBDE\nefgh\nijk\n");
+        let _ = std::fs::remove_file(&path);
+    }
+    #[test]
+    fn test_out_append() {
+        let path = tmp_path("out_append");
+        std::fs::write(&path, "ABCDE\n").unwrap();
+        {
+            let sout = FileOut::append(&path).unwrap();
+            assert!(sout.write_line("efgh".to_string()).is_ok());
+        }
+        let s = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(s, "ABCDE\nefgh\n");
+        let _ = std::fs::remove_file(&path);
+    }
+    #[test]
+    fn test_out_truncate() {
+        let path = tmp_path("out_truncate");
+        std::fs::write(&path, "ABCDE\n").unwrap();
+        {
+            let sout = FileOut::open(&path, FileMode::Truncate).unwrap();
+            assert!(sout.write_line("efgh".to_string()).is_ok());
+        }
+        let s = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(s, "efgh\n");
+        let _ = std::fs::remove_file(&path);
+    }
+    #[test]
+    fn test_out_create_new() {
+        let path = tmp_path("out_create_new");
+        {
+            let sout = FileOut::create_new(&path).unwrap();
+            assert!(sout.write_line("ABCDE".to_string()).is_ok());
+        }
+        let res = FileOut::create_new(&path);
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::AlreadyExists);
+        let _ = std::fs::remove_file(&path);
+    }
+    #[test]
+    fn test_err() {
+        let path = tmp_path("err");
+        {
+            let serr = FileErr::create(&path).unwrap();
+            #[rustfmt::skip]
+            let res = serr.lock()
+                .write_fmt(format_args!("{}\nACBDE\nefgh\n", 1234));
+            assert!(res.is_ok());
+            assert!(serr.write_line("ijk".to_string()).is_ok());
+        }
+        let s = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(s, "1234\nACBDE\nefgh\nijk\n");
+        let _ = std::fs::remove_file(&path);
+    }
+}
+
+#[cfg(test)]
+mod test_stream_ioe_fileio {
+    use runnel::*;
+    use std::path::PathBuf;
+    //
+    fn tmp_path(name: &str) -> PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "runnel-test-ioe-fileio-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+    #[test]
+    fn test_ioe() {
+        let path_in = tmp_path("in");
+        let path_out = tmp_path("out");
+        let path_err = tmp_path("err");
+        std::fs::write(&path_in, "ABCDE\nefgh\n").unwrap();
+        {
+            let sioe = RunnelIoeBuilder::new()
+                .pg_in_file(&path_in)
+                .unwrap()
+                .pg_out_file(&path_out)
+                .unwrap()
+                .pg_err_file(&path_err)
+                .unwrap()
+                .build();
+            for line in sioe.pg_in().lines().map(|l| l.unwrap()) {
+                sioe.pg_out().write_line(line.clone()).unwrap();
+                sioe.pg_err().write_line(line).unwrap();
+            }
+        }
+        assert_eq!(std::fs::read_to_string(&path_out).unwrap(), "ABCDE\nefgh\n");
+        assert_eq!(std::fs::read_to_string(&path_err).unwrap(), "ABCDE\nefgh\n");
+        let _ = std::fs::remove_file(&path_in);
+        let _ = std::fs::remove_file(&path_out);
+        let _ = std::fs::remove_file(&path_err);
+    }
+    #[test]
+    fn test_ioe_append() {
+        let path_out = tmp_path("out_append");
+        let path_err = tmp_path("err_append");
+        std::fs::write(&path_out, "ABCDE\n").unwrap();
+        std::fs::write(&path_err, "ABCDE\n").unwrap();
+        {
+            let sioe = RunnelIoeBuilder::new()
+                .fill_stringio_with_str("efgh\n")
+                .pg_out_file_append(&path_out)
+                .unwrap()
+                .pg_err_file_append(&path_err)
+                .unwrap()
+                .build();
+            for line in sioe.pg_in().lines().map(|l| l.unwrap()) {
+                sioe.pg_out().write_line(line.clone()).unwrap();
+                sioe.pg_err().write_line(line).unwrap();
+            }
+        }
+        assert_eq!(std::fs::read_to_string(&path_out).unwrap(), "ABCDE\nefgh\n");
+        assert_eq!(std::fs::read_to_string(&path_err).unwrap(), "ABCDE\nefgh\n");
+        let _ = std::fs::remove_file(&path_out);
+        let _ = std::fs::remove_file(&path_err);
+    }
+    #[test]
+    fn test_ioe_not_found() {
+        let path_in = tmp_path("not_found");
+        let res = RunnelIoeBuilder::new().pg_in_file(&path_in);
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::NotFound);
+    }
+}
@@ -106,6 +106,9 @@ mod test_stream_ioe_linepipeio {
 mod test_linepipeio_more {
     use runnel::medium::linepipeio::*;
     use runnel::*;
+    use std::io::BufRead;
+    use std::ops::ControlFlow;
+    use std::time::Duration;
 
     #[test]
     fn test_line_send_empty() {
@@ -121,4 +124,514 @@ mod test_linepipeio_more {
         assert_eq!(lines_iter.next(), None);
         assert!(handle.join().is_ok());
     }
+    #[test]
+    fn test_line_bufread() {
+        use std::io::Read;
+        let (sout, sin) = line_pipe(1);
+        let handle = std::thread::spawn(move || {
+            sout.write_line("ABCDE".to_string()).unwrap();
+            sout.write_line("".to_string()).unwrap();
+            sout.write_line("efgh".to_string()).unwrap();
+            sout.flush_line().unwrap();
+        });
+        let mut s = String::new();
+        let r = sin.lock_bufread().read_to_string(&mut s);
+        assert_eq!(r.unwrap(), 12);
+        assert_eq!(s, "ABCDE\n\nefgh\n");
+        assert!(handle.join().is_ok());
+    }
+    #[test]
+    fn test_line_bufread_then_lines() {
+        use std::io::{BufRead, Read};
+        let (sout, sin) = line_pipe(1);
+        let handle = std::thread::spawn(move || {
+            sout.write_line("ABCDE".to_string()).unwrap();
+            sout.write_line("efgh".to_string()).unwrap();
+            sout.write_line("ijklm".to_string()).unwrap();
+            sout.flush_line().unwrap();
+        });
+        {
+            let mut a = sin.lock_bufread();
+            let mut line = String::new();
+            assert_eq!(a.read_line(&mut line).unwrap(), 6);
+            assert_eq!(line, "ABCDE\n");
+            let mut buf = [0u8; 2];
+            a.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf, b"ef");
+        }
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("gh")));
+        assert_eq!(lines_iter.next(), Some(String::from("ijklm")));
+        assert_eq!(lines_iter.next(), None);
+        assert!(handle.join().is_ok());
+    }
+    #[test]
+    fn test_line_write_bytes() {
+        use std::io::Write;
+        let (sout, sin) = line_pipe(1);
+        let handle = std::thread::spawn(move || {
+            #[rustfmt::skip]
+            let res = sout.lock()
+                .write_fmt(format_args!("{}\nACBDE\nef", 1234));
+            assert!(res.is_ok());
+            assert_eq!(sout.lock().buffer_to_string(), "ef");
+            assert!(sout.lock().write_all(b"gh\nij").is_ok());
+            assert!(sout.write_line("klm".to_string()).is_ok());
+            assert!(sout.lock().write_all(b"nop").is_ok());
+            assert!(sout.lock().flush().is_ok());
+            assert_eq!(sout.lock().buffer_to_string(), "");
+        });
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("1234")));
+        assert_eq!(lines_iter.next(), Some(String::from("ACBDE")));
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(lines_iter.next(), Some(String::from("ijklm")));
+        assert_eq!(lines_iter.next(), Some(String::from("nop")));
+        assert_eq!(lines_iter.next(), None);
+        assert!(handle.join().is_ok());
+    }
+    #[test]
+    fn test_line_err_write_bytes() {
+        use std::io::Write;
+        let (sout, sin) = line_pipe(1);
+        let serr = LinePipeErr::from(sout);
+        let handle = std::thread::spawn(move || {
+            let mut a = serr.lock();
+            assert!(a.write_fmt(format_args!("{}\nACBDE", 1234)).is_ok());
+            assert!(a.flush().is_ok());
+        });
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("1234")));
+        assert_eq!(lines_iter.next(), Some(String::from("ACBDE")));
+        assert_eq!(lines_iter.next(), None);
+        assert!(handle.join().is_ok());
+    }
+    #[test]
+    fn test_line_flush_without_lines() {
+        let (sout, sin) = line_pipe(1);
+        let handle = std::thread::spawn(move || {
+            assert!(sout.flush_line().is_ok());
+            assert!(sout.write_line("ABCDE".to_string()).is_ok());
+            assert!(sout.flush_line().is_ok());
+        });
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), None);
+        assert!(handle.join().is_ok());
+    }
+    #[test]
+    fn test_line_fan_in() {
+        let (sout, sin) = line_pipe(4);
+        let mut handles = Vec::new();
+        for id in 0..4 {
+            let sout = sout.clone();
+            handles.push(std::thread::spawn(move || {
+                for i in 0..500 {
+                    let line = format!("{}:{}:", id, i) + &"x".repeat(100);
+                    assert!(sout.write_line(line).is_ok());
+                }
+                assert!(sout.flush_line().is_ok());
+            }));
+        }
+        // EOF after all clones are dropped
+        drop(sout);
+        let mut counts = [0usize; 4];
+        for line in sin.lines().map(|l| l.unwrap()) {
+            let mut it = line.splitn(3, ':');
+            let id: usize = it.next().unwrap().parse().unwrap();
+            let i: usize = it.next().unwrap().parse().unwrap();
+            assert_eq!(it.next().unwrap(), "x".repeat(100));
+            // the lines of a producer keep the order
+            assert_eq!(i, counts[id]);
+            counts[id] += 1;
+        }
+        assert_eq!(counts, [500; 4]);
+        for handle in handles {
+            assert!(handle.join().is_ok());
+        }
+    }
+    #[test]
+    fn test_line_try_fill_buf() {
+        let (sout, sin) = line_pipe(1);
+        {
+            let mut lock = sin.lock();
+            let err = lock.try_fill_buf().unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+        }
+        assert!(sout.write_line("ABCDE".to_string()).is_ok());
+        assert!(sout.flush_line().is_ok());
+        let mut lock = sin.lock();
+        assert_eq!(lock.try_fill_buf().unwrap(), b"ABCDE\n");
+        lock.consume(6);
+        drop(sout);
+        // EOF
+        assert_eq!(
+            lock.fill_buf_timeout(Duration::from_millis(10)).unwrap(),
+            b""
+        );
+    }
+    #[test]
+    fn test_line_lines_timeout() {
+        let (sout, sin) = line_pipe(4);
+        assert!(sout.write_line("ABC".to_string()).is_ok());
+        assert!(sout.flush_line().is_ok());
+        let mut lines = sin.lines_timeout(Duration::from_millis(10));
+        assert_eq!(lines.next().unwrap().unwrap(), "ABC");
+        let err = lines.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        assert!(sout.write_line("DEF".to_string()).is_ok());
+        assert!(sout.flush_line().is_ok());
+        assert_eq!(lines.next().unwrap().unwrap(), "DEF");
+        drop(sout);
+        assert!(lines.next().is_none());
+    }
+    #[test]
+    fn test_line_try_write_line() {
+        let (sout, sin) = line_pipe(1);
+        assert!(sout.try_write_line("ABC".to_string()).is_ok());
+        assert!(sout.try_flush_line().is_ok());
+        assert!(sout.try_write_line("DEF".to_string()).is_ok());
+        let err = sout.try_flush_line().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+        let err = sout.flush_timeout(Duration::from_millis(10)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        let mut lines = sin.lines_timeout(Duration::from_millis(10));
+        assert_eq!(lines.next().unwrap().unwrap(), "ABC");
+        // the lines are kept
+        assert!(sout.flush_timeout(Duration::from_millis(10)).is_ok());
+        assert_eq!(lines.next().unwrap().unwrap(), "DEF");
+    }
+    #[test]
+    fn test_line_write_timeout() {
+        let (sout, _sin) = line_pipe(1);
+        for i in 0..(2 * 1024) {
+            assert!(sout.try_write_line(format!("{}", i)).is_ok());
+        }
+        // the channel is full
+        let err = sout.try_write_line("A".to_string()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+        let err = sout
+            .write_timeout("A".to_string(), Duration::from_millis(10))
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    }
+    #[test]
+    fn test_line_close() {
+        let (sout, sin) = line_pipe(1);
+        let sout2 = sout.clone();
+        let handle = std::thread::spawn(move || {
+            assert!(sout.write_line("ABCDE".to_string()).is_ok());
+            assert!(sout.close().is_ok());
+            let err = sout.write_line("efgh".to_string()).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+            // the closing again is harmless
+            assert!(sout.close().is_ok());
+            sout
+        });
+        assert!(sout2.close().is_ok());
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        // EOF without dropping
+        assert_eq!(lines_iter.next(), None);
+        let _sout = handle.join().unwrap();
+    }
+    #[test]
+    fn test_line_reader_gone() {
+        let (sout, sin) = line_pipe(1);
+        drop(sin);
+        assert!(sout.write_line("ABCDE".to_string()).is_ok());
+        let err = sout.flush_line().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+        let err = sout.close().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+    }
+    #[test]
+    fn test_line_writer_gone() {
+        let (sout, sin) = line_pipe(1);
+        drop(sout);
+        let mut a = sin.lock_bufread();
+        assert_eq!(a.fill_buf().unwrap(), b"");
+    }
+    #[test]
+    fn test_line_flush_on_drop() {
+        let (sout, sin) = line_pipe(1);
+        let handle = std::thread::spawn(move || {
+            assert!(sout.write_line("ABCDE".to_string()).is_ok());
+            assert!(sout.write_line("efgh".to_string()).is_ok());
+            // no flush
+        });
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(lines_iter.next(), None);
+        assert!(handle.join().is_ok());
+    }
+    #[test]
+    fn test_line_drop_error_full() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        let count = Arc::new(AtomicUsize::new(0));
+        let (sout, sin) = line_pipe(1);
+        let sout = {
+            let count = count.clone();
+            sout.on_drop_error(move |_err| {
+                count.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+        assert!(sout.write_line("ABCDE".to_string()).is_ok());
+        assert!(sout.flush_line().is_ok());
+        assert!(sout.write_line("efgh".to_string()).is_ok());
+        let handle = std::thread::spawn(move || {
+            // the reader is alive, but stalls for a while
+            std::thread::sleep(std::time::Duration::from_millis(300));
+            sin.lines().map(|l| l.unwrap()).collect::<Vec<_>>()
+        });
+        // the pipe is full, the drop waits for the reader
+        drop(sout);
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+        assert_eq!(handle.join().unwrap(), ["ABCDE", "efgh"]);
+    }
+    #[test]
+    fn test_line_drop_error_full_reader_gone() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        let count = Arc::new(AtomicUsize::new(0));
+        let (sout, sin) = line_pipe(1);
+        let sout = {
+            let count = count.clone();
+            sout.on_drop_error(move |err| {
+                assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+                count.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+        assert!(sout.write_line("ABCDE".to_string()).is_ok());
+        assert!(sout.flush_line().is_ok());
+        assert!(sout.write_line("efgh".to_string()).is_ok());
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            drop(sin);
+        });
+        // the drop waits until the reader is gone, then it fails
+        drop(sout);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert!(handle.join().is_ok());
+    }
+    #[test]
+    fn test_line_drop_error() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        let count = Arc::new(AtomicUsize::new(0));
+        let (sout, sin) = line_pipe(1);
+        let sout = {
+            let count = count.clone();
+            sout.on_drop_error(move |err| {
+                assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+                count.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+        let sout2 = sout.clone();
+        drop(sin);
+        assert!(sout.write_line("ABCDE".to_string()).is_ok());
+        assert!(sout2.write_line("efgh".to_string()).is_ok());
+        drop(sout);
+        drop(sout2);
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+    #[test]
+    fn test_line_builder() {
+        let (sout, sin) = LinePipeBuilder::new()
+            .chunk_lines(2)
+            .capacity(2)
+            .build()
+            .unwrap();
+        assert!(sout.write_line("ABC".to_string()).is_ok());
+        assert_eq!(sout.lock().buffer_to_string(), "");
+        {
+            let mut lines = sin.lines_timeout(Duration::from_millis(10));
+            let err = lines.next().unwrap().unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+            // the chunk is sent without flush
+            assert!(sout.try_write_line("DEF".to_string()).is_ok());
+            assert_eq!(lines.next().unwrap().unwrap(), "ABC");
+            assert_eq!(lines.next().unwrap().unwrap(), "DEF");
+        }
+        drop(sout);
+        assert!(sin.lines().next().is_none());
+    }
+    #[test]
+    fn test_line_max_latency_full() {
+        // the pipe that nobody reads
+        let (a_out, _a_in) = LinePipeBuilder::new()
+            .capacity(1)
+            .max_latency(Duration::from_millis(10))
+            .build()
+            .unwrap();
+        assert!(a_out.write_line("ABC".to_string()).is_ok());
+        assert!(a_out.flush_line().is_ok());
+        assert!(a_out.write_line("DEF".to_string()).is_ok());
+        // the shared flusher is not blocked by the full pipe
+        let (b_out, b_in) = LinePipeBuilder::new()
+            .max_latency(Duration::from_millis(10))
+            .build()
+            .unwrap();
+        assert!(b_out.write_line("GHI".to_string()).is_ok());
+        let mut lines = b_in.lines_timeout(Duration::from_secs(5));
+        assert_eq!(lines.next().unwrap().unwrap(), "GHI");
+    }
+    #[test]
+    fn test_line_max_latency() {
+        let (sout, sin) = LinePipeBuilder::new()
+            .max_latency(Duration::from_millis(20))
+            .build()
+            .unwrap();
+        let sout2 = sout.clone();
+        assert!(sout.write_line("ABC".to_string()).is_ok());
+        assert!(sout2.write_line("DEF".to_string()).is_ok());
+        // the idle writers, without flush
+        let mut lines = sin.lines_timeout(Duration::from_secs(5));
+        let mut v = vec![
+            lines.next().unwrap().unwrap(),
+            lines.next().unwrap().unwrap(),
+        ];
+        v.sort();
+        assert_eq!(v, ["ABC", "DEF"]);
+        drop(sout);
+        drop(sout2);
+        assert!(lines.next().is_none());
+    }
+    #[test]
+    fn test_line_bytes() {
+        let (sout, sin) = line_pipe_bytes(4 * 1024);
+        let line = "x".repeat(1000);
+        let mut n = 0;
+        loop {
+            match sout.try_write_line(line.clone()) {
+                Ok(()) => {}
+                Err(err) => {
+                    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+                    break;
+                }
+            }
+            match sout.try_flush_line() {
+                Ok(()) => n += 1,
+                Err(err) => {
+                    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+                    break;
+                }
+            }
+        }
+        // the in-flight chunks are bounded by the bytes
+        assert!(n >= 1);
+        assert!(n <= 4, "n: {}", n);
+        let err = sout.flush_timeout(Duration::from_millis(10)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        let handle = std::thread::spawn(move || {
+            for _ in 0..100 {
+                assert!(sout.write_line("x".repeat(1000)).is_ok());
+                assert!(sout.flush_line().is_ok());
+            }
+        });
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.by_ref().count(), n + 101);
+        assert!(handle.join().is_ok());
+    }
+    #[test]
+    fn test_line_bytes_empty_lines() {
+        // the empty lines are counted by their `String` and the vector
+        let (sout, _sin) = line_pipe_bytes(1024);
+        let mut n = 0;
+        loop {
+            match sout.try_write_line(String::new()) {
+                Ok(()) => {}
+                Err(err) => {
+                    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+                    break;
+                }
+            }
+            match sout.try_flush_line() {
+                Ok(()) => n += 1,
+                Err(err) => {
+                    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+                    break;
+                }
+            }
+        }
+        let string_sz = std::mem::size_of::<String>();
+        assert!(n >= 1);
+        assert!(n <= 1024 / string_sz, "n: {}", n);
+    }
+    #[test]
+    fn test_line_bytes_reader_gone() {
+        let (sout, sin) = line_pipe_bytes(16);
+        assert!(sout.write_line("x".repeat(100)).is_ok());
+        assert!(sout.flush_line().is_ok());
+        assert!(sout.write_line("x".repeat(100)).is_ok());
+        let handle = std::thread::spawn(move || {
+            // this waits for the budget, until the reader is gone
+            let err = sout.flush_line().unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+        });
+        std::thread::sleep(Duration::from_millis(10));
+        drop(sin);
+        assert!(handle.join().is_ok());
+    }
+    #[test]
+    fn test_line_write_lines() {
+        let (sout, sin) = LinePipeBuilder::new().chunk_lines(3).build().unwrap();
+        // the partial line by Write is ended by the first string
+        assert!(sout.lock().write_all(b"AB").is_ok());
+        let v = vec!["C".to_string(), "DEF".to_string()];
+        assert!(sout.write_lines(v).is_ok());
+        assert!(sout.write_lines(Vec::new()).is_ok());
+        assert!(sout.write_str_line("GHI").is_ok());
+        // the chunk is sent without flush
+        let mut lines = sin.lines_timeout(Duration::from_secs(5));
+        assert_eq!(lines.next().unwrap().unwrap(), "ABC");
+        assert_eq!(lines.next().unwrap().unwrap(), "DEF");
+        assert_eq!(lines.next().unwrap().unwrap(), "GHI");
+        assert!(sout.close().is_ok());
+        let err = sout.write_lines(vec!["JKL".to_string()]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+        assert!(lines.next().is_none());
+    }
+    #[test]
+    fn test_line_for_each_line() {
+        let (sout, sin) = line_pipe(1);
+        let handle = std::thread::spawn(move || {
+            for s in ["ABC", "DEF", "GHI", "JKL"] {
+                assert!(sout.write_str_line(s).is_ok());
+            }
+            assert!(sout.flush_line().is_ok());
+        });
+        let mut v = Vec::new();
+        let r = sin.for_each_line(&mut |line| {
+            v.push(line.to_string());
+            Ok(if line == "DEF" {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            })
+        });
+        assert!(r.is_ok());
+        assert_eq!(v, ["ABC", "DEF"]);
+        let mut buf = String::from("xyz");
+        assert!(sin.read_line_into(&mut buf).unwrap());
+        assert_eq!(buf, "GHI");
+        assert!(sin.read_line_into(&mut buf).unwrap());
+        assert_eq!(buf, "JKL");
+        assert!(handle.join().is_ok());
+        assert!(!sin.read_line_into(&mut buf).unwrap());
+        assert_eq!(buf, "");
+    }
+    #[test]
+    fn test_line_read_line_into_capacity() {
+        let (sout, sin) = line_pipe(1);
+        assert!(sout.write_str_line("ABC").is_ok());
+        assert!(sout.flush_line().is_ok());
+        // the capacity of `buf` is reused
+        let mut buf = String::with_capacity(64);
+        assert!(sin.read_line_into(&mut buf).unwrap());
+        assert_eq!(buf, "ABC");
+        assert!(buf.capacity() >= 64);
+    }
 }
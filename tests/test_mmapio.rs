@@ -0,0 +1,100 @@
+#[cfg(test)]
+#[cfg(unix)]
+mod test_stream_mmapio {
+    use runnel::medium::mmapio::*;
+    use runnel::*;
+    use std::io::{BufRead, Read};
+    use std::path::PathBuf;
+    //
+    fn tmp_path(name: &str, contents: &str) -> PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "runnel-test-mmapio-{}-{}",
+            std::process::id(),
+            name
+        ));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+    #[test]
+    fn test_in() {
+        let path = tmp_path("in", "ABCDE\nefgh\r\nijk");
+        let sin = MmapIn::open(&path).unwrap();
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(lines_iter.next(), Some(String::from("ijk")));
+        assert_eq!(lines_iter.next(), None);
+        let _ = std::fs::remove_file(&path);
+    }
+    #[test]
+    fn test_in_empty() {
+        let path = tmp_path("in_empty", "");
+        let sin = MmapIn::open(&path).unwrap();
+        assert_eq!(sin.lock_bufread().fill_buf().unwrap(), b"");
+        let mut lines_iter = sin.lines();
+        assert!(lines_iter.next().is_none());
+        let _ = std::fs::remove_file(&path);
+    }
+    #[test]
+    fn test_in_bufread_whole_window() {
+        let s = "ABCDE\n".repeat(4 * 1024);
+        let path = tmp_path("in_whole", &s);
+        let sin = MmapIn::open(&path).unwrap();
+        {
+            let mut a = sin.lock_bufread();
+            assert_eq!(a.fill_buf().unwrap().len(), s.len());
+            a.consume(6);
+        }
+        let mut line = String::new();
+        let _ = sin.lock_bufread().read_line(&mut line).unwrap();
+        assert_eq!(line, "ABCDE\n");
+        let mut rest = String::new();
+        let _ = sin.lock_bufread().read_to_string(&mut rest).unwrap();
+        assert_eq!(rest.len(), s.len() - 12);
+        assert_eq!(sin.lock_bufread().fill_buf().unwrap(), b"");
+        let _ = std::fs::remove_file(&path);
+    }
+    #[test]
+    fn test_in_invalid_utf8() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("runnel-test-mmapio-{}-invalid", std::process::id()));
+        std::fs::write(&path, b"\xff\xfe\nABCDE\n").unwrap();
+        let sin = MmapIn::open(&path).unwrap();
+        let mut lines_iter = sin.lines();
+        let err = lines_iter.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(lines_iter.next().unwrap().unwrap(), "ABCDE");
+        let _ = std::fs::remove_file(&path);
+    }
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_in_proc() {
+        // the size of the files in /proc is zero by the metadata
+        let sin = MmapIn::open("/proc/self/status").unwrap();
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert!(lines_iter.next().unwrap().starts_with("Name:"));
+    }
+    #[test]
+    fn test_in_try_with() {
+        let path = tmp_path("in_try_with", "ABCDE\n");
+        let sin = MmapIn::try_with(std::fs::File::open(&path).unwrap()).unwrap();
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), None);
+        let _ = std::fs::remove_file(&path);
+    }
+    #[test]
+    fn test_ioe_mmapio() {
+        let path = tmp_path("ioe", "ABCDE\nefgh\n");
+        let sioe = RunnelIoeBuilder::new()
+            .fill_stringio_with_str("")
+            .pg_in(MmapIn::open(&path).unwrap())
+            .build();
+        for line in sioe.pg_in().lines().map(|l| l.unwrap()) {
+            sioe.pg_out().write_line(line).unwrap();
+        }
+        assert_eq!(sioe.pg_out().lock().buffer_to_string(), "ABCDE\nefgh\n");
+        let _ = std::fs::remove_file(&path);
+    }
+}
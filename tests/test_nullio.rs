@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod test_stream_nullio {
+    use runnel::medium::nullio::*;
+    use runnel::*;
+    use std::io::{BufRead, Read, Write};
+    #[test]
+    fn test_in() {
+        let sin = EmptyIn;
+        let mut lines_iter = sin.lines();
+        assert!(lines_iter.next().is_none());
+        let mut s = String::new();
+        assert_eq!(sin.lock_bufread().read_to_string(&mut s).unwrap(), 0);
+        assert!(sin.lock_bufread().fill_buf().unwrap().is_empty());
+    }
+    #[test]
+    fn test_out() {
+        let sout = NullOut;
+        #[rustfmt::skip]
+        let res = sout.lock()
+            .write_fmt(format_args!("{}\nACBDE\nefgh\n", 1234));
+        assert!(res.is_ok());
+        assert!(sout.write_line("ijklm".to_string()).is_ok());
+        assert!(sout.flush_line().is_ok());
+        assert_eq!(sout.lock().buffer_to_string(), "");
+    }
+    #[test]
+    fn test_err() {
+        let serr = NullErr;
+        #[rustfmt::skip]
+        let res = serr.lock()
+            .write_fmt(format_args!("{}\nACBDE\nefgh\n", 1234));
+        assert!(res.is_ok());
+        assert!(serr.write_line("ijklm".to_string()).is_ok());
+        assert!(serr.flush_line().is_ok());
+        assert_eq!(serr.lock().buffer_to_string(), "");
+    }
+}
+
+#[cfg(test)]
+mod test_stream_ioe_nullio {
+    use runnel::medium::stringio::StringIn;
+    use runnel::*;
+    #[test]
+    fn test_ioe() {
+        let sioe = RunnelIoeBuilder::new().fill_nullio().build();
+        assert!(sioe.pg_in().lines().next().is_none());
+        assert!(sioe.pg_out().write_line("ABCDE".to_string()).is_ok());
+        assert!(sioe.pg_err().write_line("efgh".to_string()).is_ok());
+        assert_eq!(sioe.pg_out().lock().buffer_to_string(), "");
+        assert_eq!(sioe.pg_err().lock().buffer_to_string(), "");
+    }
+    #[test]
+    fn test_ioe_quiet() {
+        let sioe = RunnelIoeBuilder::new()
+            .pg_in(StringIn::with_str("ABCDE\n"))
+            .pg_out_null()
+            .pg_err_null()
+            .build();
+        for line in sioe.pg_in().lines().map(|l| l.unwrap()) {
+            sioe.pg_out().write_line(line).unwrap();
+        }
+        assert_eq!(sioe.pg_out().lock().buffer_to_string(), "");
+        let sioe = RunnelIoeBuilder::new().pg_in_empty().pg_err_null().build();
+        assert!(sioe.pg_in().lines().next().is_none());
+    }
+}
@@ -0,0 +1,122 @@
+#[cfg(test)]
+#[cfg(unix)]
+mod test_stream_ospipeio {
+    use runnel::medium::ospipeio::*;
+    use runnel::*;
+    use std::io::{BufRead, Read, Write};
+    use std::os::unix::io::OwnedFd;
+    use std::process::{Command, Stdio};
+    #[test]
+    fn test_in_out() {
+        let (sout, sin) = os_pipe().unwrap();
+        let handler = std::thread::spawn(move || {
+            #[rustfmt::skip]
+            sout.lock().write_fmt(format_args!("{}\nACBDE\n", 1234)).unwrap();
+            sout.write_line("efgh".to_string()).unwrap();
+            sout.flush_line().unwrap();
+        });
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("1234")));
+        assert_eq!(lines_iter.next(), Some(String::from("ACBDE")));
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(lines_iter.next(), None);
+        assert!(handler.join().is_ok());
+    }
+    #[test]
+    fn test_err_bufread() {
+        let (sout, sin) = os_pipe().unwrap();
+        let serr = OsPipeErr::from(sout);
+        serr.write_line("ABCDE".to_string()).unwrap();
+        serr.flush_line().unwrap();
+        let mut s = String::new();
+        let _ = sin.lock_bufread().read_line(&mut s).unwrap();
+        assert_eq!(s, "ABCDE\n");
+        drop(serr);
+        s.clear();
+        let _ = sin.lock_bufread().read_line(&mut s).unwrap();
+        assert_eq!(s, "");
+    }
+    #[test]
+    fn test_owned_fd() {
+        let (sout, sin) = os_pipe().unwrap();
+        sout.write_line("ABCDE".to_string()).unwrap();
+        // flushed at converting
+        let fd_out = OwnedFd::try_from(sout).unwrap();
+        let fd_in = OwnedFd::try_from(sin).unwrap();
+        let sout = OsPipeOut::from(fd_out);
+        let sin = OsPipeIn::from(fd_in);
+        sout.write_line("efgh".to_string()).unwrap();
+        drop(sout);
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(lines_iter.next(), None);
+    }
+    #[test]
+    fn test_child_stdio() {
+        let (a_out, a_in) = os_pipe().unwrap();
+        let (b_out, b_in) = os_pipe().unwrap();
+        let mut child = Command::new("sort")
+            .stdin(Stdio::try_from(a_in).unwrap())
+            .stdout(Stdio::try_from(b_out).unwrap())
+            .spawn()
+            .unwrap();
+        a_out.write_line("efgh".to_string()).unwrap();
+        a_out.write_line("ABCDE".to_string()).unwrap();
+        drop(a_out);
+        let mut s = String::new();
+        b_in.lock_bufread().read_to_string(&mut s).unwrap();
+        assert_eq!(s, "ABCDE\nefgh\n");
+        assert!(child.wait().unwrap().success());
+    }
+    #[test]
+    fn test_owned_fd_buffered() {
+        let (sout, sin) = os_pipe().unwrap();
+        sout.write_line("ABCDE".to_string()).unwrap();
+        sout.write_line("efgh".to_string()).unwrap();
+        sout.flush_line().unwrap();
+        let mut s = String::new();
+        let _ = sin.lock_bufread().read_line(&mut s).unwrap();
+        assert_eq!(s, "ABCDE\n");
+        // "efgh" is in the buffer of the reader
+        let err = OwnedFd::try_from(sin).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+    #[test]
+    fn test_owned_fd_lines_taken() {
+        let (_sout, sin) = os_pipe().unwrap();
+        drop(sin.lines());
+        let err = Stdio::try_from(sin).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Other);
+    }
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod test_stream_ioe_ospipeio {
+    use runnel::medium::ospipeio::*;
+    use runnel::*;
+    #[test]
+    fn test_ioe_ospipeio() {
+        let (sout, sin) = os_pipe().unwrap();
+        //
+        #[rustfmt::skip]
+        let sioe = RunnelIoeBuilder::new().fill_stringio_with_str("ABCDE\nefgh\n")
+            .pg_out(sout).build();
+        let handler = std::thread::spawn(move || {
+            for line in sioe.pg_in().lines().map(|l| l.unwrap()) {
+                sioe.pg_out().write_line(line).unwrap();
+                sioe.pg_out().flush_line().unwrap();
+            }
+        });
+        //
+        #[rustfmt::skip]
+        let sioe = RunnelIoeBuilder::new().fill_stringio_with_str("")
+            .pg_in(sin).build();
+        let mut lines_iter = sioe.pg_in().lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(lines_iter.next(), None);
+        assert!(handler.join().is_ok());
+    }
+}
@@ -191,7 +191,8 @@ mod test_stream_ioe_pipeio {
 mod test_pipeio_more {
     use runnel::medium::pipeio::*;
     use runnel::*;
-    use std::io::Write;
+    use std::io::{BufRead, Write};
+    use std::time::Duration;
 
     #[test]
     fn test_pipe_send_empty() {
@@ -206,4 +207,494 @@ mod test_pipeio_more {
         assert_eq!(lines_iter.next(), None);
         assert!(handle.join().is_ok());
     }
+    #[test]
+    fn test_pipe_flush_empty_not_eof() {
+        let (sout, sin) = pipe(1);
+        let handle = std::thread::spawn(move || {
+            assert!(sout.flush_line().is_ok());
+            assert!(sout.write_line("ABCDE".to_string()).is_ok());
+            assert!(sout.flush_line().is_ok());
+        });
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), None);
+        assert!(handle.join().is_ok());
+    }
+    #[test]
+    fn test_pipe_many_flush() {
+        // the buffers are recycled between the both sides
+        let (sout, sin) = pipe(1);
+        let handle = std::thread::spawn(move || {
+            for i in 0..1000 {
+                assert!(sout.write_line(format!("{}", i)).is_ok());
+                assert!(sout.flush_line().is_ok());
+            }
+        });
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        for i in 0..1000 {
+            assert_eq!(lines_iter.next(), Some(format!("{}", i)));
+        }
+        assert_eq!(lines_iter.next(), None);
+        assert!(handle.join().is_ok());
+    }
+    #[test]
+    fn test_pipe_flush_closed_keeps_buffer() {
+        let (sout, sin) = pipe(1);
+        drop(sin);
+        assert!(sout.write_line("ABCDE".to_string()).is_ok());
+        assert!(sout.flush_line().is_err());
+        assert_eq!(sout.lock().buffer_to_string(), "ABCDE\n");
+    }
+    #[test]
+    fn test_pipe_fan_in() {
+        let (sout, sin) = pipe(4);
+        let mut handles = Vec::new();
+        for id in 0..4 {
+            let sout = sout.clone();
+            handles.push(std::thread::spawn(move || {
+                for i in 0..500 {
+                    let line = format!("{}:{}:", id, i) + &"x".repeat(100);
+                    assert!(sout.write_line(line).is_ok());
+                }
+                assert!(sout.flush_line().is_ok());
+            }));
+        }
+        // EOF after all clones are dropped
+        drop(sout);
+        let mut counts = [0usize; 4];
+        for line in sin.lines().map(|l| l.unwrap()) {
+            let mut it = line.splitn(3, ':');
+            let id: usize = it.next().unwrap().parse().unwrap();
+            let i: usize = it.next().unwrap().parse().unwrap();
+            assert_eq!(it.next().unwrap(), "x".repeat(100));
+            // the lines of a producer keep the order
+            assert_eq!(i, counts[id]);
+            counts[id] += 1;
+        }
+        assert_eq!(counts, [500; 4]);
+        for handle in handles {
+            assert!(handle.join().is_ok());
+        }
+    }
+    #[test]
+    fn test_pipe_try_fill_buf() {
+        let (sout, sin) = pipe(1);
+        {
+            let mut lock = sin.lock();
+            let err = lock.try_fill_buf().unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+        }
+        assert!(sout.write_line("ABCDE".to_string()).is_ok());
+        assert!(sout.flush_line().is_ok());
+        let mut lock = sin.lock();
+        assert_eq!(lock.try_fill_buf().unwrap(), b"ABCDE\n");
+        lock.consume(6);
+        drop(sout);
+        // EOF
+        assert_eq!(
+            lock.fill_buf_timeout(Duration::from_millis(10)).unwrap(),
+            b""
+        );
+    }
+    #[test]
+    fn test_pipe_lines_timeout() {
+        let (sout, sin) = pipe(4);
+        assert!(sout.write_line("ABC".to_string()).is_ok());
+        {
+            let mut lock = sout.lock();
+            assert!(lock.write_all(b"DE").is_ok());
+            assert!(lock.flush().is_ok());
+        }
+        let mut lines = sin.lines_timeout(Duration::from_millis(10));
+        assert_eq!(lines.next().unwrap().unwrap(), "ABC");
+        let err = lines.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        // the partial line is kept
+        assert!(sout.write_line("FG".to_string()).is_ok());
+        assert!(sout.flush_line().is_ok());
+        assert_eq!(lines.next().unwrap().unwrap(), "DEFG");
+        drop(sout);
+        assert!(lines.next().is_none());
+    }
+    #[test]
+    fn test_pipe_try_write_line() {
+        let (sout, sin) = pipe(1);
+        assert!(sout.try_write_line("ABC".to_string()).is_ok());
+        assert!(sout.try_flush_line().is_ok());
+        assert!(sout.try_write_line("DEF".to_string()).is_ok());
+        let err = sout.try_flush_line().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+        let err = sout.flush_timeout(Duration::from_millis(10)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        // the buffer is kept
+        assert_eq!(sout.lock().buffer_to_string(), "DEF\n");
+        let mut lines = sin.lines_timeout(Duration::from_millis(10));
+        assert_eq!(lines.next().unwrap().unwrap(), "ABC");
+        assert!(sout.flush_timeout(Duration::from_millis(10)).is_ok());
+        assert_eq!(lines.next().unwrap().unwrap(), "DEF");
+    }
+    #[test]
+    fn test_pipe_write_timeout() {
+        let (sout, _sin) = pipe(1);
+        let big = "x".repeat(16 * 1024);
+        // the first fills the buffer, the second sends it
+        assert!(sout
+            .write_timeout(big.clone(), Duration::from_millis(10))
+            .is_ok());
+        assert!(sout
+            .write_timeout("A".to_string(), Duration::from_millis(10))
+            .is_ok());
+        // the channel is full
+        assert!(sout
+            .write_timeout(big.clone(), Duration::from_millis(10))
+            .is_ok());
+        let err = sout
+            .write_timeout("B".to_string(), Duration::from_millis(10))
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        let err = sout.try_write_line("B".to_string()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+    }
+    #[test]
+    fn test_pipe_close() {
+        let (sout, sin) = pipe(1);
+        let sout2 = sout.clone();
+        let handle = std::thread::spawn(move || {
+            assert!(sout.write_line("ABCDE".to_string()).is_ok());
+            assert!(sout.close().is_ok());
+            let err = sout.write_line("efgh".to_string()).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+            // the closing again is harmless
+            assert!(sout.close().is_ok());
+            sout
+        });
+        assert!(sout2.close().is_ok());
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        // EOF without dropping
+        assert_eq!(lines_iter.next(), None);
+        let _sout = handle.join().unwrap();
+    }
+    #[test]
+    fn test_pipe_reader_gone() {
+        let (sout, sin) = pipe(1);
+        drop(sin);
+        assert!(sout.write_line("ABCDE".to_string()).is_ok());
+        let err = sout.flush_line().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+        let err = sout.close().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+    }
+    #[test]
+    fn test_pipe_writer_gone() {
+        let (sout, sin) = pipe(1);
+        drop(sout);
+        let mut a = sin.lock_bufread();
+        assert_eq!(a.fill_buf().unwrap(), b"");
+    }
+    #[test]
+    fn test_pipe_flush_on_drop() {
+        let (sout, sin) = pipe(1);
+        let handle = std::thread::spawn(move || {
+            assert!(sout.write_line("ABCDE".to_string()).is_ok());
+            assert!(sout.write_line("efgh".to_string()).is_ok());
+            // no flush
+        });
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(lines_iter.next(), None);
+        assert!(handle.join().is_ok());
+    }
+    #[test]
+    fn test_pipe_drop_error_full() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        let count = Arc::new(AtomicUsize::new(0));
+        let (sout, sin) = pipe(1);
+        let sout = {
+            let count = count.clone();
+            sout.on_drop_error(move |_err| {
+                count.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+        assert!(sout.write_line("ABCDE".to_string()).is_ok());
+        assert!(sout.flush_line().is_ok());
+        assert!(sout.write_line("efgh".to_string()).is_ok());
+        let handle = std::thread::spawn(move || {
+            // the reader is alive, but stalls for a while
+            std::thread::sleep(std::time::Duration::from_millis(300));
+            sin.lines().map(|l| l.unwrap()).collect::<Vec<_>>()
+        });
+        // the pipe is full, the drop waits for the reader
+        drop(sout);
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+        assert_eq!(handle.join().unwrap(), ["ABCDE", "efgh"]);
+    }
+    #[test]
+    fn test_pipe_drop_error_full_reader_gone() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        let count = Arc::new(AtomicUsize::new(0));
+        let (sout, sin) = pipe(1);
+        let sout = {
+            let count = count.clone();
+            sout.on_drop_error(move |err| {
+                assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+                count.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+        assert!(sout.write_line("ABCDE".to_string()).is_ok());
+        assert!(sout.flush_line().is_ok());
+        assert!(sout.write_line("efgh".to_string()).is_ok());
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            drop(sin);
+        });
+        // the drop waits until the reader is gone, then it fails
+        drop(sout);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert!(handle.join().is_ok());
+    }
+    #[test]
+    fn test_pipe_drop_error() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        let count = Arc::new(AtomicUsize::new(0));
+        let (sout, sin) = pipe(1);
+        let sout = {
+            let count = count.clone();
+            sout.on_drop_error(move |err| {
+                assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+                count.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+        let sout2 = sout.clone();
+        drop(sin);
+        assert!(sout.write_line("ABCDE".to_string()).is_ok());
+        assert!(sout2.write_line("efgh".to_string()).is_ok());
+        drop(sout);
+        drop(sout2);
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+    #[test]
+    fn test_pipe_builder() {
+        let (sout, sin) = PipeBuilder::new()
+            .capacity(2)
+            .chunk_bytes(4)
+            .read_buf(3)
+            .build()
+            .unwrap();
+        {
+            let mut lock = sout.lock();
+            assert!(lock.write_all(b"AB").is_ok());
+            assert_eq!(lock.buffer(), b"AB");
+            // the chunk is sent without flush
+            assert!(lock.write_all(b"CDE").is_ok());
+            assert_eq!(lock.buffer(), b"");
+        }
+        let mut lock = sin.lock();
+        assert_eq!(lock.try_fill_buf().unwrap(), b"ABC");
+        lock.consume(3);
+        assert_eq!(lock.try_fill_buf().unwrap(), b"DE");
+        lock.consume(2);
+        let err = lock.try_fill_buf().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+    }
+    #[test]
+    fn test_pipe_max_latency_full() {
+        // the pipe that nobody reads
+        let (a_out, _a_in) = PipeBuilder::new()
+            .capacity(1)
+            .max_latency(Duration::from_millis(10))
+            .build()
+            .unwrap();
+        assert!(a_out.write_line("ABC".to_string()).is_ok());
+        assert!(a_out.flush_line().is_ok());
+        assert!(a_out.write_line("DEF".to_string()).is_ok());
+        // the shared flusher is not blocked by the full pipe
+        let (b_out, b_in) = PipeBuilder::new()
+            .max_latency(Duration::from_millis(10))
+            .build()
+            .unwrap();
+        assert!(b_out.write_line("GHI".to_string()).is_ok());
+        let mut lines = b_in.lines_timeout(Duration::from_secs(5));
+        assert_eq!(lines.next().unwrap().unwrap(), "GHI");
+    }
+    #[test]
+    fn test_pipe_max_latency_drop() {
+        let (sout, sin) = PipeBuilder::new()
+            .max_latency(Duration::from_millis(1))
+            .build()
+            .unwrap();
+        let me = std::thread::current().id();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let sout = sout.on_drop_error(move |_err| {
+            tx.send(std::thread::current().id()).unwrap();
+        });
+        drop(sin);
+        assert!(sout.write_line("ABC".to_string()).is_ok());
+        // the final flush runs on the dropping thread, not the flusher
+        drop(sout);
+        assert_eq!(rx.try_recv().ok(), Some(me));
+    }
+    #[test]
+    fn test_pipe_max_latency() {
+        let (sout, sin) = PipeBuilder::new()
+            .max_latency(Duration::from_millis(20))
+            .build()
+            .unwrap();
+        let sout2 = sout.clone();
+        assert!(sout.write_line("ABC".to_string()).is_ok());
+        assert!(sout2.write_line("DEF".to_string()).is_ok());
+        // the idle writers, without flush
+        let mut lines = sin.lines_timeout(Duration::from_secs(5));
+        let mut v = vec![
+            lines.next().unwrap().unwrap(),
+            lines.next().unwrap().unwrap(),
+        ];
+        v.sort();
+        assert_eq!(v, ["ABC", "DEF"]);
+        drop(sout);
+        drop(sout2);
+        assert!(lines.next().is_none());
+    }
+    #[test]
+    fn test_pipe_buffer_mode() {
+        let (sout, sin) = PipeBuilder::new()
+            .capacity(4)
+            .buffer_mode(BufferMode::Line)
+            .build()
+            .unwrap();
+        {
+            let mut lock = sout.lock();
+            assert!(lock.write_all(b"AB").is_ok());
+            assert_eq!(lock.buffer(), b"AB");
+            assert!(lock.write_all(b"C\nDE").is_ok());
+            assert_eq!(lock.buffer(), b"");
+        }
+        assert!(sout.write_line("FG".to_string()).is_ok());
+        assert_eq!(sout.lock().buffer(), b"");
+        let mut lines = sin.lines_timeout(Duration::from_millis(10));
+        assert_eq!(lines.next().unwrap().unwrap(), "ABC");
+        assert_eq!(lines.next().unwrap().unwrap(), "DEFG");
+        //
+        let (sout, sin) = PipeBuilder::new()
+            .capacity(4)
+            .buffer_mode(BufferMode::Unbuffered)
+            .build()
+            .unwrap();
+        assert!(sout.lock().write_all(b"AB").is_ok());
+        assert_eq!(sout.lock().buffer(), b"");
+        let mut lock = sin.lock();
+        assert_eq!(lock.try_fill_buf().unwrap(), b"AB");
+    }
+    #[test]
+    fn test_pipe_buffer_mode_broken_pipe() {
+        let (sout, sin) = PipeBuilder::new()
+            .capacity(4)
+            .buffer_mode(BufferMode::Line)
+            .build()
+            .unwrap();
+        drop(sin);
+        // the reader is gone, the line is not sent
+        let err = sout.write_line("ABC".to_string()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+        let err = sout.lock().write_all(b"DEF\n").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+    }
+    #[test]
+    fn test_pipe_bytes() {
+        let (sout, sin) = pipe_bytes(4 * 1024);
+        let line = "x".repeat(1000);
+        let mut n = 0;
+        loop {
+            match sout.try_write_line(line.clone()) {
+                Ok(()) => {}
+                Err(err) => {
+                    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+                    break;
+                }
+            }
+            match sout.try_flush_line() {
+                Ok(()) => n += 1,
+                Err(err) => {
+                    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+                    break;
+                }
+            }
+        }
+        // the in-flight chunks are bounded by the bytes
+        assert!(n >= 1);
+        assert!(n <= 4, "n: {}", n);
+        let err = sout.flush_timeout(Duration::from_millis(10)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        let handle = std::thread::spawn(move || {
+            for _ in 0..100 {
+                assert!(sout.write_line("x".repeat(1000)).is_ok());
+                assert!(sout.flush_line().is_ok());
+            }
+        });
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.by_ref().count(), n + 101);
+        assert!(handle.join().is_ok());
+    }
+    #[test]
+    fn test_pipe_bytes_reader_gone() {
+        let (sout, sin) = pipe_bytes(16);
+        assert!(sout.write_line("x".repeat(100)).is_ok());
+        assert!(sout.flush_line().is_ok());
+        assert!(sout.write_line("x".repeat(100)).is_ok());
+        let handle = std::thread::spawn(move || {
+            // this waits for the budget, until the reader is gone
+            let err = sout.flush_line().unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+        });
+        std::thread::sleep(Duration::from_millis(10));
+        drop(sin);
+        assert!(handle.join().is_ok());
+    }
+    #[test]
+    fn test_pipe_write_lines() {
+        let (sout, sin) = pipe(1);
+        let handle = std::thread::spawn(move || {
+            let v = vec!["ABCDE".to_string(), "efgh".to_string()];
+            assert!(sout.write_lines(v).is_ok());
+            assert!(sout.write_str_line("ijk").is_ok());
+            assert!(sout.flush_line().is_ok());
+        });
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(lines_iter.next(), Some(String::from("ijk")));
+        assert_eq!(lines_iter.next(), None);
+        assert!(handle.join().is_ok());
+    }
+    #[test]
+    fn test_pipe_for_each_line() {
+        let (sout, sin) = pipe(1);
+        let handle = std::thread::spawn(move || {
+            for s in ["ABC", "DEF", "GHI"] {
+                assert!(sout.write_str_line(s).is_ok());
+            }
+            assert!(sout.flush_line().is_ok());
+        });
+        let mut v = Vec::new();
+        let r = sin.for_each_line(&mut |line| {
+            v.push(line.to_string());
+            Ok(if line == "DEF" {
+                std::ops::ControlFlow::Break(())
+            } else {
+                std::ops::ControlFlow::Continue(())
+            })
+        });
+        assert!(r.is_ok());
+        assert_eq!(v, ["ABC", "DEF"]);
+        let mut buf = String::from("xyz");
+        assert!(sin.read_line_into(&mut buf).unwrap());
+        assert_eq!(buf, "GHI");
+        assert!(handle.join().is_ok());
+        assert!(!sin.read_line_into(&mut buf).unwrap());
+        assert_eq!(buf, "");
+    }
 }
@@ -0,0 +1,147 @@
+#[cfg(test)]
+mod test_stream_ringpipeio {
+    use runnel::medium::ringpipeio::*;
+    use runnel::*;
+    use std::io::{BufRead, Read, Write};
+    #[test]
+    fn test_in_out() {
+        let (sout, sin) = ring_pipe(1024);
+        let handler = std::thread::spawn(move || {
+            #[rustfmt::skip]
+            let res = sout.lock()
+                .write_fmt(format_args!("{}\nACBDE\nefgh\n", 1234));
+            assert!(res.is_ok());
+            assert!(sout.flush_line().is_ok());
+            assert_eq!(sout.lock().buffer_to_string(), "");
+        });
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("1234")));
+        assert_eq!(lines_iter.next(), Some(String::from("ACBDE")));
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(lines_iter.next(), None);
+        assert!(handler.join().is_ok());
+    }
+    #[test]
+    fn test_small_ring() {
+        // the ring wraps around many times, and both sides wait
+        let (sout, sin) = ring_pipe(7);
+        let handler = std::thread::spawn(move || {
+            for i in 0..2000 {
+                assert!(sout.write_line(format!("{:05}", i)).is_ok());
+            }
+        });
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        for i in 0..2000 {
+            assert_eq!(lines_iter.next(), Some(format!("{:05}", i)));
+        }
+        assert_eq!(lines_iter.next(), None);
+        assert!(handler.join().is_ok());
+    }
+    #[test]
+    fn test_bufread() {
+        let (sout, sin) = ring_pipe(16);
+        let handler = std::thread::spawn(move || {
+            let mut out = sout.lock();
+            for _ in 0..100 {
+                out.write_all(b"0123456789").unwrap();
+            }
+        });
+        let mut buf = Vec::new();
+        let mut a = sin.lock_bufread();
+        loop {
+            let len = {
+                let src = a.fill_buf().unwrap();
+                assert!(src.len() <= 16);
+                buf.extend_from_slice(src);
+                src.len()
+            };
+            if len == 0 {
+                break;
+            }
+            a.consume(len);
+        }
+        assert_eq!(buf, b"0123456789".repeat(100));
+        let mut s = String::new();
+        assert_eq!(a.read_to_string(&mut s).unwrap(), 0);
+        assert!(handler.join().is_ok());
+    }
+    #[test]
+    fn test_over_consume() {
+        let (sout, sin) = ring_pipe(16);
+        sout.lock().write_all(b"ABC\n").unwrap();
+        {
+            let mut a = sin.lock_bufread();
+            assert_eq!(a.fill_buf().unwrap(), b"ABC\n");
+            // past the written data, it is clamped
+            a.consume(100);
+        }
+        // the writer is not blocked, and the reader sees the new data only
+        sout.lock().write_all(b"efgh\n").unwrap();
+        drop(sout);
+        let mut s = String::new();
+        assert!(sin.lock_bufread().read_to_string(&mut s).is_ok());
+        assert_eq!(s, "efgh\n");
+    }
+    #[test]
+    fn test_broken_pipe() {
+        let (sout, sin) = ring_pipe(4);
+        drop(sin);
+        let r = sout.write_line("ABCDE".to_string());
+        assert_eq!(r.unwrap_err().kind(), std::io::ErrorKind::BrokenPipe);
+    }
+    #[test]
+    fn test_broken_pipe_while_waiting() {
+        let (sout, sin) = ring_pipe(4);
+        let handler = std::thread::spawn(move || sout.write_line("ABCDEFGH".to_string()));
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        drop(sin);
+        let r = handler.join().unwrap();
+        assert_eq!(r.unwrap_err().kind(), std::io::ErrorKind::BrokenPipe);
+    }
+    #[test]
+    fn test_err() {
+        let (sout, sin) = ring_pipe(1024);
+        let serr = RingPipeErr::from(sout);
+        assert!(serr.write_line("ABCDE".to_string()).is_ok());
+        assert!(serr.flush_line().is_ok());
+        drop(serr);
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), None);
+    }
+}
+
+#[cfg(test)]
+mod test_stream_ioe_ringpipeio {
+    use runnel::medium::ringpipeio::*;
+    use runnel::*;
+    #[test]
+    fn test_ioe_ringpipeio() {
+        let (a_out, a_in) = ring_pipe(64);
+        let (b_out, b_in) = ring_pipe(64);
+        #[rustfmt::skip]
+        let sioe = RunnelIoeBuilder::new().fill_stringio_with_str("ABCDE\nefgh\n")
+            .pg_out(a_out).build();
+        let handler1 = std::thread::spawn(move || {
+            for line in sioe.pg_in().lines().map(|l| l.unwrap()) {
+                sioe.pg_out().write_line(line).unwrap();
+            }
+        });
+        #[rustfmt::skip]
+        let sioe = RunnelIoeBuilder::new().pg_in(a_in).pg_out(b_out).build();
+        let handler2 = std::thread::spawn(move || {
+            for line in sioe.pg_in().lines().map(|l| l.unwrap()) {
+                sioe.pg_out().write_line(line).unwrap();
+            }
+        });
+        #[rustfmt::skip]
+        let sioe = RunnelIoeBuilder::new().fill_stringio_with_str("")
+            .pg_in(b_in).build();
+        for line in sioe.pg_in().lines().map(|l| l.unwrap()) {
+            sioe.pg_out().write_line(line).unwrap();
+        }
+        assert!(handler1.join().is_ok());
+        assert!(handler2.join().is_ok());
+        assert_eq!(sioe.pg_out().lock().buffer_to_string(), "ABCDE\nefgh\n");
+    }
+}
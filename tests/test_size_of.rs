@@ -33,8 +33,8 @@ mod test_size_of_linepipeio {
     #[rustversion::since(1.67)]
     #[test]
     fn test_size_of_linepipe_in_out() {
-        assert_eq!(std::mem::size_of::<LinePipeIn>(), 48);
-        assert_eq!(std::mem::size_of::<LinePipeOut>(), 48);
+        assert_eq!(std::mem::size_of::<LinePipeIn>(), 104);
+        assert_eq!(std::mem::size_of::<LinePipeOut>(), 8);
     }
 }
 
@@ -50,35 +50,11 @@ mod test_size_of_pipeio {
         assert_eq!(std::mem::size_of::<PipeOutLock>(), 16);
     }
     //
-    #[rustversion::all(since(1.59), before(1.62))]
-    #[test]
-    fn test_size_of_pipe_in_out() {
-        assert_eq!(std::mem::size_of::<PipeIn>(), 112);
-        assert_eq!(std::mem::size_of::<PipeOut>(), 48);
-    }
-    #[rustversion::all(since(1.62), before(1.64))]
-    #[test]
-    fn test_size_of_pipe_in_out() {
-        assert_eq!(std::mem::size_of::<PipeIn>(), 104);
-        assert_eq!(std::mem::size_of::<PipeOut>(), 40);
-    }
-    #[rustversion::all(since(1.64), before(1.65))]
-    #[test]
-    fn test_size_of_pipe_in_out() {
-        assert_eq!(std::mem::size_of::<PipeIn>(), 96);
-        assert_eq!(std::mem::size_of::<PipeOut>(), 40);
-    }
-    #[rustversion::all(since(1.65), before(1.67))]
-    #[test]
-    fn test_size_of_pipe_in_out() {
-        assert_eq!(std::mem::size_of::<PipeIn>(), 104);
-        assert_eq!(std::mem::size_of::<PipeOut>(), 40);
-    }
     #[rustversion::since(1.67)]
     #[test]
     fn test_size_of_pipe_in_out() {
-        assert_eq!(std::mem::size_of::<PipeIn>(), 104);
-        assert_eq!(std::mem::size_of::<PipeOut>(), 48);
+        assert_eq!(std::mem::size_of::<PipeIn>(), 144);
+        assert_eq!(std::mem::size_of::<PipeOut>(), 8);
     }
 }
 
@@ -91,21 +67,21 @@ mod test_size_of_stdio {
         #[cfg(target_arch = "x86_64")]
         {
             assert_eq!(std::mem::size_of::<StdIn>(), 8);
-            assert_eq!(std::mem::size_of::<StdOut>(), 8);
+            assert_eq!(std::mem::size_of::<StdOut>(), 56);
             assert_eq!(std::mem::size_of::<StdErr>(), 8);
             //
             assert_eq!(std::mem::size_of::<StdInLock>(), 16);
-            assert_eq!(std::mem::size_of::<StdOutLock>(), 8);
+            assert_eq!(std::mem::size_of::<StdOutLock>(), 32);
             assert_eq!(std::mem::size_of::<StdErrLock>(), 8);
         }
         #[cfg(target_arch = "x86")]
         {
             assert_eq!(std::mem::size_of::<StdIn>(), 4);
-            assert_eq!(std::mem::size_of::<StdOut>(), 4);
+            assert_eq!(std::mem::size_of::<StdOut>(), 32);
             assert_eq!(std::mem::size_of::<StdErr>(), 4);
             //
             assert_eq!(std::mem::size_of::<StdInLock>(), 8);
-            assert_eq!(std::mem::size_of::<StdOutLock>(), 4);
+            assert_eq!(std::mem::size_of::<StdOutLock>(), 16);
             assert_eq!(std::mem::size_of::<StdErrLock>(), 4);
         }
     }
@@ -124,21 +100,7 @@ mod test_size_of_stringio {
         assert_eq!(std::mem::size_of::<StringErrLock>(), 16);
     }
     //
-    #[rustversion::before(1.59)]
-    #[test]
-    fn test_size_of_string_in_out_err() {
-        assert_eq!(std::mem::size_of::<StringIn>(), 88);
-        assert_eq!(std::mem::size_of::<StringOut>(), 40);
-        assert_eq!(std::mem::size_of::<StringErr>(), 40);
-    }
-    #[rustversion::all(since(1.59), before(1.62))]
-    #[test]
-    fn test_size_of_string_in_out_err() {
-        assert_eq!(std::mem::size_of::<StringIn>(), 96);
-        assert_eq!(std::mem::size_of::<StringOut>(), 40);
-        assert_eq!(std::mem::size_of::<StringErr>(), 40);
-    }
-    #[rustversion::all(since(1.62), before(1.64))]
+    #[rustversion::before(1.64)]
     #[test]
     fn test_size_of_string_in_out_err() {
         assert_eq!(std::mem::size_of::<StringIn>(), 88);
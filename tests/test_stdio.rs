@@ -1,4 +1,67 @@
 #[cfg(test)]
+#[cfg(unix)]
 mod test_stdio {
-    //use runnel::medium::stdio::*;
+    use runnel::medium::stdio::*;
+    use runnel::*;
+    use std::io::Write;
+    use std::process::Command;
+    //
+    const MODE_ENV: &str = "RUNNEL_TEST_STDOUT_MODE";
+    //
+    // this is run in the child process only, see `run_child()`
+    #[test]
+    #[ignore]
+    fn test_child_stdout() {
+        let mode = match std::env::var(MODE_ENV).as_deref() {
+            Ok("unbuffered") => BufferMode::Unbuffered,
+            Ok("line") => BufferMode::Line,
+            Ok("full") => BufferMode::Full,
+            _ => return,
+        };
+        let sout = StdOut::with_buffer_mode(mode);
+        sout.write_line("A".to_string()).unwrap();
+        sout.lock().write_all(b"C").unwrap();
+        if mode == BufferMode::Full {
+            assert_eq!(sout.lock().buffer(), b"A\nC");
+        } else {
+            assert_eq!(sout.lock().buffer(), b"");
+        }
+        // write to the fd 1 directly, without the buffers
+        {
+            use std::os::unix::io::FromRawFd;
+            let mut raw = std::mem::ManuallyDrop::new(unsafe { std::fs::File::from_raw_fd(1) });
+            raw.write_all(b"B").unwrap();
+        }
+        drop(sout);
+        std::io::stdout().flush().unwrap();
+    }
+    //
+    fn run_child(mode: &str) -> String {
+        let output = Command::new(std::env::current_exe().unwrap())
+            .arg("test_stdio::test_child_stdout")
+            .arg("--exact")
+            .arg("--ignored")
+            .arg("--nocapture")
+            .arg("-q")
+            .env(MODE_ENV, mode)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    }
+    #[test]
+    fn test_unbuffered() {
+        // each write is sent at once
+        assert!(run_child("unbuffered").contains("A\nCB"));
+    }
+    #[test]
+    fn test_line() {
+        // the partial line is kept until its newline or the flush
+        assert!(run_child("line").contains("A\nBC"));
+    }
+    #[test]
+    fn test_full() {
+        // all is kept until the flush on drop
+        assert!(run_child("full").contains("BA\nC"));
+    }
 }
@@ -145,4 +145,36 @@ mod test_stringio_more {
         assert_eq!(res.unwrap(), 0);
         assert_eq!(sout.lock().buffer(), b"");
     }
+
+    #[test]
+    fn test_string_out_write_lines() {
+        let sout = StringOut::default();
+        let v = vec!["ABCDE".to_string(), "efgh".to_string()];
+        assert!(sout.write_lines(v).is_ok());
+        assert!(sout.write_str_line("ijk").is_ok());
+        assert_eq!(sout.lock().buffer_to_string(), "ABCDE\nefgh\nijk\n");
+    }
+
+    #[test]
+    fn test_string_in_for_each_line() {
+        let sin = StringIn::with_str("ABC\r\nDEF\nGHI\nJKL");
+        let mut v = Vec::new();
+        let r = sin.for_each_line(&mut |line| {
+            v.push(line.to_string());
+            Ok(if line == "DEF" {
+                std::ops::ControlFlow::Break(())
+            } else {
+                std::ops::ControlFlow::Continue(())
+            })
+        });
+        assert!(r.is_ok());
+        assert_eq!(v, ["ABC", "DEF"]);
+        let mut buf = String::from("xyz");
+        assert!(sin.read_line_into(&mut buf).unwrap());
+        assert_eq!(buf, "GHI");
+        assert!(sin.read_line_into(&mut buf).unwrap());
+        assert_eq!(buf, "JKL");
+        assert!(!sin.read_line_into(&mut buf).unwrap());
+        assert_eq!(buf, "");
+    }
 }
@@ -0,0 +1,129 @@
+#[cfg(test)]
+mod test_stream_tcpio {
+    use runnel::medium::tcpio::*;
+    use runnel::*;
+    use std::io::{BufRead, Write};
+    use std::net::{TcpListener, TcpStream};
+    //
+    fn stream_pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let a = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (b, _) = listener.accept().unwrap();
+        (a, b)
+    }
+    #[test]
+    fn test_in_out() {
+        let (a, b) = stream_pair();
+        let sout = TcpOut::with(a);
+        let sin = TcpIn::with(b);
+        let handler = std::thread::spawn(move || {
+            #[rustfmt::skip]
+            sout.lock().write_fmt(format_args!("{}\nACBDE\n", 1234)).unwrap();
+            sout.write_line("efgh".to_string()).unwrap();
+            sout.flush_line().unwrap();
+        });
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("1234")));
+        assert_eq!(lines_iter.next(), Some(String::from("ACBDE")));
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(lines_iter.next(), None);
+        assert!(handler.join().is_ok());
+    }
+    #[test]
+    fn test_err_bufread() {
+        let (a, b) = stream_pair();
+        let serr = TcpErr::with(a);
+        let sin = TcpIn::with(b);
+        serr.write_line("ABCDE".to_string()).unwrap();
+        serr.flush_line().unwrap();
+        let mut s = String::new();
+        let _ = sin.lock_bufread().read_line(&mut s).unwrap();
+        assert_eq!(s, "ABCDE\n");
+        drop(serr);
+        s.clear();
+        let _ = sin.lock_bufread().read_line(&mut s).unwrap();
+        assert_eq!(s, "");
+    }
+    #[test]
+    fn test_split_eof_on_drop() {
+        let (a, b) = stream_pair();
+        let (a_out, _a_in) = split(a).unwrap();
+        let (_b_out, b_in) = split(b).unwrap();
+        a_out.write_line("ABCDE".to_string()).unwrap();
+        // the read half `_a_in` is still alive
+        drop(a_out);
+        let mut lines_iter = b_in.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), None);
+    }
+}
+
+#[cfg(test)]
+mod test_stream_ioe_tcpio {
+    use runnel::medium::tcpio::*;
+    use runnel::*;
+    //
+    fn run(sioe: &RunnelIoe) {
+        for line in sioe.pg_in().lines().map(|l| l.unwrap()) {
+            sioe.pg_out().write_line(line.to_uppercase()).unwrap();
+        }
+    }
+    #[test]
+    fn test_ioe_listener() {
+        let listener = TcpIoeListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handler = std::thread::spawn(move || {
+            for sioe in listener.incoming().take(2) {
+                run(&sioe.unwrap());
+            }
+        });
+        for s in ["abcde", "efgh"] {
+            let (sout, sin) = connect(addr).unwrap();
+            sout.write_line(s.to_string()).unwrap();
+            drop(sout);
+            let mut lines_iter = sin.lines().map(|l| l.unwrap());
+            assert_eq!(lines_iter.next(), Some(s.to_uppercase()));
+            assert_eq!(lines_iter.next(), None);
+        }
+        assert!(handler.join().is_ok());
+    }
+    #[test]
+    fn test_ioe_listener_accept() {
+        let listener = TcpIoeListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handler = std::thread::spawn(move || {
+            let (sioe, _peer) = listener.accept().unwrap();
+            let s = format!("{:?}", sioe);
+            assert!(s.contains("pg_err: StdErr(Stderr { .. })"));
+            run(&sioe);
+        });
+        let (sout, sin) = connect(addr).unwrap();
+        sout.write_line("ijk".to_string()).unwrap();
+        drop(sout);
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("IJK")));
+        assert_eq!(lines_iter.next(), None);
+        assert!(handler.join().is_ok());
+    }
+    #[test]
+    fn test_ioe_listener_interactive() {
+        let listener = TcpIoeListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handler = std::thread::spawn(move || {
+            let (sioe, _peer) = listener.accept().unwrap();
+            run(&sioe);
+        });
+        let (sout, sin) = connect(addr).unwrap();
+        // each request is answered while the connection is open
+        for s in ["abcde", "efgh", "ijk"] {
+            sout.write_line(s.to_string()).unwrap();
+            let mut line = String::new();
+            assert!(sin.read_line_into(&mut line).unwrap());
+            assert_eq!(line, s.to_uppercase());
+        }
+        drop(sout);
+        let mut line = String::new();
+        assert!(!sin.read_line_into(&mut line).unwrap());
+        assert!(handler.join().is_ok());
+    }
+}
@@ -0,0 +1,146 @@
+#[cfg(test)]
+mod test_stream_teeio {
+    use runnel::medium::pipeio::{pipe, PipeErr};
+    use runnel::medium::stringio::{StringErr, StringOut};
+    use runnel::medium::teeio::*;
+    use runnel::*;
+    use std::io::Write;
+    #[test]
+    fn test_out() {
+        let tee = TeeOut::with(vec![
+            Box::new(StringOut::default()),
+            Box::new(StringOut::default()),
+        ]);
+        #[rustfmt::skip]
+        let res = tee.lock()
+            .write_fmt(format_args!("{}\nACBDE\n", 1234));
+        assert!(res.is_ok());
+        assert!(tee.write_line("efgh".to_string()).is_ok());
+        assert!(tee.flush_line().is_ok());
+        assert_eq!(tee.lock().buffer_to_string(), "");
+        for idx in 0..2 {
+            assert_eq!(
+                tee.branch(idx).unwrap().lock().buffer_to_string(),
+                "1234\nACBDE\nefgh\n"
+            );
+        }
+        assert!(tee.branch(2).is_none());
+    }
+    #[test]
+    fn test_out_write_lines() {
+        let tee = TeeOut::with(vec![
+            Box::new(StringOut::default()),
+            Box::new(StringOut::default()),
+        ]);
+        let v = vec!["ABCDE".to_string(), "efgh".to_string()];
+        assert!(tee.write_lines(v).is_ok());
+        assert!(tee.write_str_line("ijk").is_ok());
+        for idx in 0..2 {
+            assert_eq!(
+                tee.branch(idx).unwrap().lock().buffer_to_string(),
+                "ABCDE\nefgh\nijk\n"
+            );
+        }
+    }
+    #[test]
+    fn test_out_pipe() {
+        let (sout, sin) = pipe(1);
+        let tee = TeeOut::with(vec![Box::new(sout), Box::new(StringOut::default())]);
+        let handler = std::thread::spawn(move || {
+            let mut lines_iter = sin.lines().map(|l| l.unwrap());
+            assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+            assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+            assert_eq!(lines_iter.next(), None);
+        });
+        tee.write_line("ABCDE".to_string()).unwrap();
+        tee.write_line("efgh".to_string()).unwrap();
+        tee.flush_line().unwrap();
+        let s = tee.branch(1).unwrap().lock().buffer_to_string();
+        drop(tee);
+        assert!(handler.join().is_ok());
+        assert_eq!(s, "ABCDE\nefgh\n");
+    }
+    #[test]
+    fn test_policy_fail_fast() {
+        let (sout, sin) = pipe(1);
+        drop(sin);
+        let tee = TeeOut::with(vec![Box::new(sout), Box::new(StringOut::default())]);
+        tee.write_line("ABCDE".to_string()).unwrap();
+        assert!(tee.flush_line().is_err());
+        assert!(tee.is_branch_alive(0));
+        // the rest branch is not written
+        let res = tee.lock().write(b"efgh\n");
+        assert!(res.is_ok());
+        assert!(tee.lock().flush().is_err());
+    }
+    #[test]
+    fn test_policy_ignore() {
+        let (sout, sin) = pipe(1);
+        drop(sin);
+        let tee = TeeOut::with_policy(
+            vec![Box::new(sout), Box::new(StringOut::default())],
+            TeePolicy::Ignore,
+        );
+        tee.write_line("ABCDE".to_string()).unwrap();
+        assert!(tee.flush_line().is_ok());
+        assert!(tee.is_branch_alive(0));
+        assert_eq!(tee.branch(1).unwrap().lock().buffer_to_string(), "ABCDE\n");
+    }
+    #[test]
+    fn test_policy_drop_branch() {
+        let (sout, sin) = pipe(1);
+        drop(sin);
+        let tee = TeeOut::with_policy(
+            vec![Box::new(sout), Box::new(StringOut::default())],
+            TeePolicy::DropBranch,
+        );
+        tee.write_line("ABCDE".to_string()).unwrap();
+        assert!(tee.flush_line().is_ok());
+        assert!(!tee.is_branch_alive(0));
+        assert!(tee.is_branch_alive(1));
+        tee.write_line("efgh".to_string()).unwrap();
+        assert!(tee.flush_line().is_ok());
+        assert_eq!(tee.branch(0).unwrap().lock().buffer_to_string(), "ABCDE\n");
+        assert_eq!(
+            tee.branch(1).unwrap().lock().buffer_to_string(),
+            "ABCDE\nefgh\n"
+        );
+    }
+    #[test]
+    fn test_err() {
+        let (sout, sin) = pipe(1);
+        drop(sin);
+        let tee = TeeErr::with_policy(
+            vec![
+                Box::new(PipeErr::from(sout)),
+                Box::new(StringErr::default()),
+            ],
+            TeePolicy::DropBranch,
+        );
+        #[rustfmt::skip]
+        let res = tee.lock()
+            .write_fmt(format_args!("{}\nACBDE\n", 1234));
+        assert!(res.is_ok());
+        assert!(tee.lock().flush().is_ok());
+        assert!(!tee.is_branch_alive(0));
+        assert!(tee.write_line("efgh".to_string()).is_ok());
+        assert_eq!(tee.lock().buffer_to_string(), "");
+        assert_eq!(
+            tee.branch(1).unwrap().lock().buffer_to_string(),
+            "1234\nACBDE\nefgh\n"
+        );
+    }
+    #[test]
+    fn test_drop_branch_all() {
+        let (sout, sin) = pipe(1);
+        drop(sin);
+        let tee = TeeOut::with_policy(vec![Box::new(sout)], TeePolicy::DropBranch);
+        tee.write_line("ABCDE".to_string()).unwrap();
+        // the last branch is dropped, the data goes nowhere
+        let err = tee.flush_line().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+        assert!(!tee.is_branch_alive(0));
+        let err = tee.write_line("efgh".to_string()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+    }
+}
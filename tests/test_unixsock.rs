@@ -0,0 +1,109 @@
+#[cfg(test)]
+#[cfg(unix)]
+mod test_stream_unixsock {
+    use runnel::medium::unixsock::*;
+    use runnel::*;
+    use std::io::{BufRead, Write};
+    use std::os::unix::net::UnixStream;
+    #[test]
+    fn test_in_out() {
+        let (a, b) = UnixStream::pair().unwrap();
+        let sout = UnixStreamOut::with(a);
+        let sin = UnixStreamIn::with(b);
+        let handler = std::thread::spawn(move || {
+            #[rustfmt::skip]
+            sout.lock().write_fmt(format_args!("{}\nACBDE\n", 1234)).unwrap();
+            sout.write_line("efgh".to_string()).unwrap();
+            sout.flush_line().unwrap();
+        });
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("1234")));
+        assert_eq!(lines_iter.next(), Some(String::from("ACBDE")));
+        assert_eq!(lines_iter.next(), Some(String::from("efgh")));
+        assert_eq!(lines_iter.next(), None);
+        assert!(handler.join().is_ok());
+    }
+    #[test]
+    fn test_err_bufread() {
+        let (a, b) = UnixStream::pair().unwrap();
+        let serr = UnixStreamErr::with(a);
+        let sin = UnixStreamIn::with(b);
+        serr.write_line("ABCDE".to_string()).unwrap();
+        serr.flush_line().unwrap();
+        let mut s = String::new();
+        let _ = sin.lock_bufread().read_line(&mut s).unwrap();
+        assert_eq!(s, "ABCDE\n");
+        drop(serr);
+        s.clear();
+        let _ = sin.lock_bufread().read_line(&mut s).unwrap();
+        assert_eq!(s, "");
+    }
+    #[test]
+    fn test_split_eof_on_drop() {
+        let (a, b) = UnixStream::pair().unwrap();
+        let (a_out, a_in) = split(a).unwrap();
+        let (b_out, b_in) = split(b).unwrap();
+        a_out.write_line("ABCDE".to_string()).unwrap();
+        // the read half `a_in` is still alive
+        drop(a_out);
+        let mut lines_iter = b_in.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), None);
+        drop(b_out);
+        let mut lines_iter = a_in.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), None);
+    }
+    #[test]
+    fn test_connect() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("runnel-test-unixsock-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        let handler = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let (sout, sin) = split(stream).unwrap();
+            for line in sin.lines().map(|l| l.unwrap()) {
+                sout.write_line(line.to_uppercase()).unwrap();
+            }
+        });
+        let (sout, sin) = connect(&path).unwrap();
+        sout.write_line("abcde".to_string()).unwrap();
+        sout.write_line("efgh".to_string()).unwrap();
+        drop(sout);
+        let mut lines_iter = sin.lines().map(|l| l.unwrap());
+        assert_eq!(lines_iter.next(), Some(String::from("ABCDE")));
+        assert_eq!(lines_iter.next(), Some(String::from("EFGH")));
+        assert_eq!(lines_iter.next(), None);
+        assert!(handler.join().is_ok());
+        let _ = std::fs::remove_file(&path);
+    }
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod test_stream_ioe_unixsock {
+    use runnel::medium::unixsock::*;
+    #[test]
+    fn test_ioe_pair() {
+        let (sioe_a, sioe_b) = ioe_pair().unwrap();
+        let handler = std::thread::spawn(move || {
+            for line in sioe_b.pg_in().lines().map(|l| l.unwrap()) {
+                sioe_b
+                    .pg_out()
+                    .write_line(format!("{}{}", line, line))
+                    .unwrap();
+                sioe_b.pg_out().flush_line().unwrap();
+            }
+        });
+        sioe_a.pg_out().write_line("ABCDE".to_string()).unwrap();
+        sioe_a.pg_out().write_line("efgh".to_string()).unwrap();
+        sioe_a.pg_out().flush_line().unwrap();
+        {
+            let mut lines_iter = sioe_a.pg_in().lines().map(|l| l.unwrap());
+            assert_eq!(lines_iter.next(), Some(String::from("ABCDEABCDE")));
+            assert_eq!(lines_iter.next(), Some(String::from("efghefgh")));
+        }
+        drop(sioe_a);
+        assert!(handler.join().is_ok());
+    }
+}
@@ -2,7 +2,7 @@ use criterion::{criterion_group, criterion_main, Criterion};
 use runnel::medium::linepipeio::*;
 use runnel::*;
 
-fn process_one(cnt: usize) -> () {
+fn process_one(cnt: usize) {
     let (sout1, sin1) = line_pipe(1);
     let (sout2, sin2) = line_pipe(1);
     let (sout3, sin3) = line_pipe(1);
@@ -64,7 +64,7 @@ fn process_one(cnt: usize) -> () {
 fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("linepipeio::", |b| {
         b.iter(|| {
-            let _r = process_one(std::hint::black_box(8 * 4 * 1024));
+            process_one(std::hint::black_box(8 * 4 * 1024));
         })
     });
 }
@@ -3,7 +3,7 @@ use runnel::medium::pipeio::*;
 use runnel::*;
 use std::io::Write;
 
-fn process_one(cnt: usize) -> () {
+fn process_one(cnt: usize) {
     let (sout1, sin1) = pipe(1);
     let (sout2, sin2) = pipe(1);
     let (sout3, sin3) = pipe(1);
@@ -77,7 +77,7 @@ fn process_one(cnt: usize) -> () {
 fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("pipeio::", |b| {
         b.iter(|| {
-            let _r = process_one(std::hint::black_box(8 * 4 * 1024));
+            process_one(std::hint::black_box(8 * 4 * 1024));
         })
     });
 }
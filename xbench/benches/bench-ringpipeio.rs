@@ -0,0 +1,93 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use runnel::medium::ringpipeio::*;
+use runnel::*;
+use std::io::Write;
+
+fn process_one(cnt: usize) {
+    let (sout1, sin1) = ring_pipe(64 * 1024);
+    let (sout2, sin2) = ring_pipe(64 * 1024);
+    let (sout3, sin3) = ring_pipe(64 * 1024);
+    let (sout4, sin4) = ring_pipe(64 * 1024);
+    //
+    #[rustfmt::skip]
+    let sioe = RunnelIoeBuilder::new().pg_out(sout1).build();
+    let handler1 = std::thread::spawn(move || {
+        let mut i = 0;
+        loop {
+            i += 1;
+            if i > cnt {
+                break;
+            }
+            let line = i.to_string().repeat(200);
+            sioe.pg_out()
+                .lock()
+                .write_fmt(format_args!("{}\n", line))
+                .unwrap();
+        }
+        sioe.pg_out().lock().flush().unwrap();
+    });
+    #[rustfmt::skip]
+    let sioe = RunnelIoeBuilder::new().pg_in(sin1).pg_out(sout2).build();
+    let handler2 = std::thread::spawn(move || {
+        for line in sioe.pg_in().lines().map(|l| l.unwrap()) {
+            // nothing todo
+            sioe.pg_out()
+                .lock()
+                .write_fmt(format_args!("{}\n", line))
+                .unwrap();
+        }
+        sioe.pg_out().lock().flush().unwrap();
+    });
+    #[rustfmt::skip]
+    let sioe = RunnelIoeBuilder::new().pg_in(sin2).pg_out(sout3).build();
+    let handler3 = std::thread::spawn(move || {
+        for line in sioe.pg_in().lines().map(|l| l.unwrap()) {
+            // nothing todo
+            sioe.pg_out()
+                .lock()
+                .write_fmt(format_args!("{}\n", line))
+                .unwrap();
+        }
+        sioe.pg_out().lock().flush().unwrap();
+    });
+    #[rustfmt::skip]
+    let sioe = RunnelIoeBuilder::new().pg_in(sin3).pg_out(sout4).build();
+    let handler4 = std::thread::spawn(move || {
+        for line in sioe.pg_in().lines().map(|l| l.unwrap()) {
+            // nothing todo
+            sioe.pg_out()
+                .lock()
+                .write_fmt(format_args!("{}\n", line))
+                .unwrap();
+        }
+        sioe.pg_out().lock().flush().unwrap();
+    });
+    //
+    #[rustfmt::skip]
+    let sioe = RunnelIoeBuilder::new().pg_in(sin4).build();
+    for _line in sioe.pg_in().lines().map(|l| l.unwrap()) {
+        // nothing todo
+    }
+    let _ = handler1.join();
+    let _ = handler2.join();
+    let _ = handler3.join();
+    let _ = handler4.join();
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    c.bench_function("ringpipeio::", |b| {
+        b.iter(|| {
+            process_one(std::hint::black_box(8 * 4 * 1024));
+        })
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default()
+        .warm_up_time(std::time::Duration::from_millis(300))
+        .measurement_time(std::time::Duration::from_millis(20000));
+    targets = criterion_benchmark
+}
+//criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);